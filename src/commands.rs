@@ -1,6 +1,7 @@
 //! Rustic Subcommands
 
 pub(crate) mod backup;
+pub(crate) mod biggest;
 pub(crate) mod cat;
 pub(crate) mod check;
 pub(crate) mod completions;
@@ -44,12 +45,12 @@ use crate::commands::webdav::WebDavCmd;
 use crate::{
     Application, RUSTIC_APP,
     commands::{
-        backup::BackupCmd, cat::CatCmd, check::CheckCmd, completions::CompletionsCmd,
-        config::ConfigCmd, copy::CopyCmd, diff::DiffCmd, docs::DocsCmd, dump::DumpCmd,
-        forget::ForgetCmd, init::InitCmd, key::KeyCmd, list::ListCmd, ls::LsCmd, merge::MergeCmd,
-        prune::PruneCmd, repair::RepairCmd, repoinfo::RepoInfoCmd, restore::RestoreCmd,
-        self_update::SelfUpdateCmd, show_config::ShowConfigCmd, snapshots::SnapshotCmd,
-        tag::TagCmd,
+        backup::BackupCmd, biggest::BiggestCmd, cat::CatCmd, check::CheckCmd,
+        completions::CompletionsCmd, config::ConfigCmd, copy::CopyCmd, diff::DiffCmd,
+        docs::DocsCmd, dump::DumpCmd, forget::ForgetCmd, init::InitCmd, key::KeyCmd, list::ListCmd,
+        ls::LsCmd, merge::MergeCmd, prune::PruneCmd, repair::RepairCmd, repoinfo::RepoInfoCmd,
+        restore::RestoreCmd, self_update::SelfUpdateCmd, show_config::ShowConfigCmd,
+        snapshots::SnapshotCmd, tag::TagCmd,
     },
     config::RusticConfig,
 };
@@ -78,6 +79,9 @@ enum RusticCmd {
     /// Backup to the repository
     Backup(Box<BackupCmd>),
 
+    /// Show the biggest files within a snapshot
+    Biggest(Box<BiggestCmd>),
+
     /// Show raw data of files and blobs in a repository
     Cat(Box<CatCmd>),
 
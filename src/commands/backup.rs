@@ -313,7 +313,7 @@ impl BackupCmd {
             let add_entry = |title: &str, value: String| {
                 _ = table.add_row([bold_cell(title), Cell::new(value)]);
             };
-            fill_table(&snap, add_entry);
+            fill_table(&snap, config.global.time_zone(), add_entry);
 
             println!("{table}");
         } else if !self.quiet {
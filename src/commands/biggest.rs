@@ -0,0 +1,83 @@
+//! `biggest` subcommand
+
+use std::{cmp::Reverse, collections::BinaryHeap, path::Path};
+
+use crate::{
+    Application, RUSTIC_APP,
+    helpers::{bytes_size_to_string, resolve_snapshot_description},
+    repository::CliIndexedRepo,
+    status_err,
+};
+
+use abscissa_core::{Command, Runnable, Shutdown};
+use anyhow::Result;
+
+use rustic_core::LsOptions;
+
+/// `biggest` subcommand
+#[derive(clap::Parser, Command, Debug)]
+pub(crate) struct BiggestCmd {
+    /// Snapshot/path to search in
+    #[clap(value_name = "SNAPSHOT[:PATH]")]
+    snap: String,
+
+    /// Number of files to show
+    #[clap(long, value_name = "N", default_value = "10")]
+    top: usize,
+}
+
+impl Runnable for BiggestCmd {
+    fn run(&self) {
+        if let Err(err) = RUSTIC_APP
+            .config()
+            .repository
+            .run_indexed(|repo| self.inner_run(repo))
+        {
+            status_err!("{}", err);
+            RUSTIC_APP.shutdown(Shutdown::Crash);
+        };
+    }
+}
+
+impl BiggestCmd {
+    fn inner_run(&self, repo: CliIndexedRepo) -> Result<()> {
+        let config = RUSTIC_APP.config();
+
+        let snap = resolve_snapshot_description(&repo, &self.snap, |sn| {
+            config.snapshot_filter.matches(sn)
+        })?;
+        let node = repo.node_from_snapshot_path(&snap, |sn| config.snapshot_filter.matches(sn))?;
+
+        let ls_opts = LsOptions::default().recursive(true);
+
+        // keep a bounded min-heap, so we only hold the `top` biggest files in memory
+        let mut heap = BinaryHeap::with_capacity(self.top + 1);
+        for item in repo.ls(&node, &ls_opts)? {
+            let (path, node) = item?;
+            if !node.is_file() {
+                continue;
+            }
+            heap.push(Reverse((node.meta.size, path)));
+            if heap.len() > self.top {
+                _ = heap.pop();
+            }
+        }
+
+        // sorting `Reverse` items ascending yields the biggest file first
+        for Reverse((size, path)) in heap.into_sorted_vec() {
+            print_entry(size, &path);
+        }
+
+        Ok(())
+    }
+}
+
+/// Print a single entry with its size
+///
+/// # Arguments
+///
+/// * `size` - the size of the file
+/// * `path` - the path of the file
+fn print_entry(size: u64, path: &Path) {
+    println!("{:>10} {}", bytes_size_to_string(size), path.display());
+}
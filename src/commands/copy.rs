@@ -131,12 +131,13 @@ impl CopyCmd {
             snapshots,
         )?;
 
+        let zone = config.global.time_zone();
         let mut table =
             table_with_titles(["ID", "Time", "Host", "Label", "Tags", "Paths", "Status"]);
         for CopySnapshot { relevant, sn } in &snaps {
             let tags = sn.tags.formatln();
             let paths = sn.paths.formatln();
-            let time = sn.time.format("%Y-%m-%d %H:%M:%S").to_string();
+            let time = zone.format(&sn.time, "%Y-%m-%d %H:%M:%S");
             _ = table.add_row([
                 &sn.id.to_string(),
                 &time,
@@ -2,11 +2,16 @@
 
 use std::{
     fs::File,
-    io::{Cursor, Read, Seek, SeekFrom, Write, copy},
+    io::{BufReader, Cursor, Read, Seek, SeekFrom, Write, copy},
     path::PathBuf,
 };
 
-use crate::{Application, RUSTIC_APP, repository::CliIndexedRepo, status_err};
+use crate::{
+    Application, RUSTIC_APP,
+    helpers::{parse_chunk_size, resolve_snapshot_description},
+    repository::CliIndexedRepo,
+    status_err,
+};
 
 use abscissa_core::{Command, Runnable, Shutdown};
 use anyhow::Result;
@@ -36,6 +41,10 @@ pub(crate) struct DumpCmd {
     #[clap(long)]
     file: Option<PathBuf>,
 
+    /// number of bytes to request per read from the repository. Larger values can help on high-latency backends, smaller values reduce memory usage.
+    #[clap(long, value_name = "SIZE", default_value = "1MiB", value_parser = parse_chunk_size)]
+    chunk_size: usize,
+
     /// Glob pattern to exclude/include (can be specified multiple times)
     #[clap(long, help_heading = "Exclude options")]
     glob: Vec<String>,
@@ -79,8 +88,10 @@ impl DumpCmd {
     fn inner_run(&self, repo: CliIndexedRepo) -> Result<()> {
         let config = RUSTIC_APP.config();
 
-        let node =
-            repo.node_from_snapshot_path(&self.snap, |sn| config.snapshot_filter.matches(sn))?;
+        let snap = resolve_snapshot_description(&repo, &self.snap, |sn| {
+            config.snapshot_filter.matches(sn)
+        })?;
+        let node = repo.node_from_snapshot_path(&snap, |sn| config.snapshot_filter.matches(sn))?;
 
         let stdout = std::io::stdout();
 
@@ -111,7 +122,7 @@ impl DumpCmd {
             let mut file = File::create(file)?;
             if archive == ArchiveKind::Zip {
                 // when writing zip to a file, we use the optimized writer
-                return write_zip_to_file(&repo, &node, &mut file, &ls_opts);
+                return write_zip_to_file(&repo, &node, &mut file, &ls_opts, self.chunk_size);
             }
             Box::new(file)
         } else {
@@ -119,10 +130,10 @@ impl DumpCmd {
         };
 
         match archive {
-            ArchiveKind::Content => dump_content(&repo, &node, &mut w, &ls_opts)?,
-            ArchiveKind::Tar => dump_tar(&repo, &node, &mut w, &ls_opts)?,
-            ArchiveKind::TarGz => dump_tar_gz(&repo, &node, &mut w, &ls_opts)?,
-            ArchiveKind::Zip => dump_zip(&repo, &node, &mut w, &ls_opts)?,
+            ArchiveKind::Content => dump_content(&repo, &node, &mut w, &ls_opts, self.chunk_size)?,
+            ArchiveKind::Tar => dump_tar(&repo, &node, &mut w, &ls_opts, self.chunk_size)?,
+            ArchiveKind::TarGz => dump_tar_gz(&repo, &node, &mut w, &ls_opts, self.chunk_size)?,
+            ArchiveKind::Zip => dump_zip(&repo, &node, &mut w, &ls_opts, self.chunk_size)?,
             _ => {}
         };
 
@@ -135,22 +146,37 @@ fn dump_content(
     node: &Node,
     w: &mut impl Write,
     ls_opts: &LsOptions,
+    chunk_size: usize,
 ) -> Result<()> {
     for item in repo.ls(node, ls_opts)? {
         let (_, node) = item?;
-        repo.dump(&node, w)?;
+        if node.is_file() {
+            dump_file(repo, &node, w, chunk_size)?;
+        }
     }
     Ok(())
 }
 
+fn dump_file(
+    repo: &CliIndexedRepo,
+    node: &Node,
+    w: &mut impl Write,
+    chunk_size: usize,
+) -> Result<()> {
+    let mut reader = BufReader::with_capacity(chunk_size, OpenFileReader::new(repo, node)?);
+    _ = copy(&mut reader, w)?;
+    Ok(())
+}
+
 fn dump_tar_gz(
     repo: &CliIndexedRepo,
     node: &Node,
     w: &mut impl Write,
     ls_opts: &LsOptions,
+    chunk_size: usize,
 ) -> Result<()> {
     let mut w = GzEncoder::new(w, Compression::default());
-    dump_tar(repo, node, &mut w, ls_opts)
+    dump_tar(repo, node, &mut w, ls_opts, chunk_size)
 }
 
 fn dump_tar(
@@ -158,6 +184,7 @@ fn dump_tar(
     node: &Node,
     w: &mut impl Write,
     ls_opts: &LsOptions,
+    chunk_size: usize,
 ) -> Result<()> {
     let mut ar = Builder::new(w);
     for item in repo.ls(node, ls_opts)? {
@@ -215,11 +242,7 @@ fn dump_tar(
 
         if node.is_file() {
             // write file content if this is a regular file
-            let open_file = OpenFileReader {
-                repo,
-                open_file: repo.open_file(&node)?,
-                offset: 0,
-            };
+            let open_file = BufReader::with_capacity(chunk_size, OpenFileReader::new(repo, &node)?);
             ar.append_data(&mut header, path, open_file)?;
         } else {
             let data: &[u8] = &[];
@@ -236,6 +259,7 @@ fn dump_zip(
     node: &Node,
     w: &mut impl Write,
     ls_opts: &LsOptions,
+    chunk_size: usize,
 ) -> Result<()> {
     let w = SeekWriter {
         write: w,
@@ -244,7 +268,7 @@ fn dump_zip(
     };
     let mut zip = ZipWriter::new(w);
     zip.set_flush_on_finish_file(true);
-    write_zip_contents(repo, node, &mut zip, ls_opts)?;
+    write_zip_contents(repo, node, &mut zip, ls_opts, chunk_size)?;
     let mut inner = zip.finish()?;
     inner.flush()?;
     Ok(())
@@ -255,9 +279,10 @@ fn write_zip_to_file(
     node: &Node,
     file: &mut (impl Write + Seek),
     ls_opts: &LsOptions,
+    chunk_size: usize,
 ) -> Result<()> {
     let mut zip = ZipWriter::new(file);
-    write_zip_contents(repo, node, &mut zip, ls_opts)?;
+    write_zip_contents(repo, node, &mut zip, ls_opts, chunk_size)?;
     let _ = zip.finish()?;
     Ok(())
 }
@@ -267,6 +292,7 @@ fn write_zip_contents(
     node: &Node,
     zip: &mut ZipWriter<impl Write + Seek>,
     ls_opts: &LsOptions,
+    chunk_size: usize,
 ) -> Result<()> {
     for item in repo.ls(node, ls_opts)? {
         let (path, node) = item?;
@@ -282,7 +308,7 @@ fn write_zip_contents(
         }
         if node.is_file() {
             zip.start_file_from_path(path, options)?;
-            repo.dump(&node, zip)?;
+            dump_file(repo, &node, zip, chunk_size)?;
         } else {
             zip.add_directory_from_path(path, options)?;
         }
@@ -336,6 +362,16 @@ struct OpenFileReader<'a> {
     offset: usize,
 }
 
+impl<'a> OpenFileReader<'a> {
+    fn new(repo: &'a CliIndexedRepo, node: &Node) -> Result<Self> {
+        Ok(Self {
+            repo,
+            open_file: repo.open_file(node)?,
+            offset: 0,
+        })
+    }
+}
+
 impl Read for OpenFileReader<'_> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         let data = self
@@ -2,7 +2,9 @@
 
 use std::path::{Path, PathBuf};
 
-use crate::{Application, RUSTIC_APP, repository::CliIndexedRepo, status_err};
+use crate::{
+    Application, RUSTIC_APP, helpers::DisplayTimeZone, repository::CliIndexedRepo, status_err,
+};
 
 use abscissa_core::{Command, Runnable, Shutdown};
 use anyhow::Result;
@@ -15,7 +17,7 @@ use rustic_core::{
     repofile::{Node, SnapshotFile},
 };
 
-use super::ls::print_node;
+use super::ls::{LongWidths, print_node};
 
 /// `find` subcommand
 #[derive(clap::Parser, Command, Debug)]
@@ -78,6 +80,8 @@ impl FindCmd {
         let groups = repo.get_snapshot_group(&self.ids, self.group_by, |sn| {
             config.snapshot_filter.matches(sn)
         })?;
+        let zone = config.global.time_zone();
+        let mut widths = LongWidths::default();
         for (group, mut snapshots) in groups {
             snapshots.sort_unstable();
             if !group.is_empty() {
@@ -91,9 +95,21 @@ impl FindCmd {
                     .zip(snapshots.iter())
                     .chunk_by(|(idx, _)| *idx)
                 {
-                    self.print_identical_snapshots(idx.iter(), g.into_iter().map(|(_, sn)| sn));
+                    self.print_identical_snapshots(
+                        idx.iter(),
+                        g.into_iter().map(|(_, sn)| sn),
+                        zone,
+                    );
                     if let Some(idx) = idx {
-                        print_node(&nodes[*idx], path, self.numeric_id);
+                        print_node(
+                            &nodes[*idx],
+                            path,
+                            &mut widths,
+                            self.numeric_id,
+                            false,
+                            None,
+                            zone,
+                        );
                     }
                 }
             } else {
@@ -118,9 +134,21 @@ impl FindCmd {
                     .zip(snapshots.iter())
                     .chunk_by(|(idx, _)| *idx)
                 {
-                    self.print_identical_snapshots(idx.iter(), g.into_iter().map(|(_, sn)| sn));
+                    self.print_identical_snapshots(
+                        idx.iter(),
+                        g.into_iter().map(|(_, sn)| sn),
+                        zone,
+                    );
                     for (path_idx, node_idx) in idx {
-                        print_node(&nodes[*node_idx], &paths[*path_idx], self.numeric_id);
+                        print_node(
+                            &nodes[*node_idx],
+                            &paths[*path_idx],
+                            &mut widths,
+                            self.numeric_id,
+                            false,
+                            None,
+                            zone,
+                        );
                     }
                 }
             }
@@ -132,19 +160,20 @@ impl FindCmd {
         &self,
         mut idx: impl Iterator,
         mut g: impl Iterator<Item = &'a SnapshotFile>,
+        zone: DisplayTimeZone,
     ) {
         let empty_result = idx.next().is_none();
         let not = if empty_result { "not " } else { "" };
         if self.show_misses || !empty_result {
             if self.all {
                 for sn in g {
-                    let time = sn.time.format("%Y-%m-%d %H:%M:%S");
+                    let time = zone.format(&sn.time, "%Y-%m-%d %H:%M:%S");
                     println!("{not}found in {} from {time}", sn.id);
                 }
             } else {
                 let sn = g.next().unwrap();
                 let count = g.count();
-                let time = sn.time.format("%Y-%m-%d %H:%M:%S");
+                let time = zone.format(&sn.time, "%Y-%m-%d %H:%M:%S");
                 match count {
                     0 => println!("{not}found in {} from {time}", sn.id),
                     count => println!("{not}found in {} from {time} (+{count})", sn.id),
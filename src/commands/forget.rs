@@ -1,7 +1,11 @@
 //! `forget` subcommand
 
 use crate::repository::CliOpenRepo;
-use crate::{Application, RUSTIC_APP, RusticConfig, helpers::table_with_titles, status_err};
+use crate::{
+    Application, RUSTIC_APP, RusticConfig,
+    helpers::{DisplayTimeZone, table_with_titles},
+    status_err,
+};
 
 use abscissa_core::{Command, FrameworkError, Runnable};
 use abscissa_core::{Shutdown, config::Override};
@@ -144,7 +148,7 @@ impl ForgetCmd {
             let mut stdout = std::io::stdout();
             serde_json::to_writer_pretty(&mut stdout, &groups)?;
         } else if !self.quiet {
-            print_groups(&groups);
+            print_groups(&groups, config.global.time_zone());
         }
 
         let forget_snaps = groups.into_forget_ids();
@@ -175,7 +179,7 @@ impl ForgetCmd {
 /// # Arguments
 ///
 /// * `groups` - forget groups to print
-fn print_groups(groups: &ForgetGroups) {
+fn print_groups(groups: &ForgetGroups, zone: DisplayTimeZone) {
     for ForgetGroup { group, snapshots } in &groups.0 {
         let mut table = table_with_titles([
             "ID", "Time", "Host", "Label", "Tags", "Paths", "Action", "Reason",
@@ -187,7 +191,7 @@ fn print_groups(groups: &ForgetGroups) {
             reasons,
         } in snapshots
         {
-            let time = sn.time.format("%Y-%m-%d %H:%M:%S").to_string();
+            let time = zone.format(&sn.time, "%Y-%m-%d %H:%M:%S");
             let tags = sn.tags.formatln();
             let paths = sn.paths.formatln();
             let action = if *keep { "keep" } else { "remove" };
@@ -1,18 +1,40 @@
 //! `ls` subcommand
 
 use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::OsStr,
+    fs,
+    io::{self, IsTerminal, Write},
     ops::{Add, AddAssign},
-    path::Path,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
 };
 
-use crate::{Application, RUSTIC_APP, repository::CliIndexedRepo, status_err};
+use crate::{
+    Application, RUSTIC_APP,
+    duration::RelativeDuration,
+    filtering::SnapshotFilter,
+    helpers::{
+        DisplayTimeZone, NotFound, TimeFormat, bytes_size_to_string, check_snapshot_prefix,
+        resolve_snapshot_description, table_with_titles,
+    },
+    mime::{MimeCache, mime_matches},
+    repository::{CliIndexedRepo, CliOpenRepo},
+    status_err,
+};
 
 use abscissa_core::{Command, Runnable, Shutdown};
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
+use bytesize::ByteSize;
+use chrono::{DateTime, Local, NaiveTime};
+use itertools::Either;
+use log::{info, warn};
+use serde::Serialize;
 
 use rustic_core::{
-    LsOptions,
-    repofile::{Node, NodeType},
+    DataId, LsOptions, Progress, ProgressBars, RusticResult, TreeId,
+    repofile::{Node, NodeType, SnapshotFile},
 };
 
 mod constants {
@@ -31,43 +53,376 @@ mod constants {
 }
 use constants::{S_IRGRP, S_IROTH, S_IRUSR, S_IWGRP, S_IWOTH, S_IWUSR, S_IXGRP, S_IXOTH, S_IXUSR};
 
+/// Exit code of `ls` if the snapshot (or the path within it) was not found
+const EXIT_NOT_FOUND: i32 = 2;
+
+/// Exit code of `ls` if no entry was listed, i.e. the listed dir is empty or all entries were filtered out
+const EXIT_NO_ENTRIES: i32 = 3;
+
+/// The exit code of `ls` for `err` if it differs from the one for other errors
+fn error_exit_code(err: &anyhow::Error) -> Option<i32> {
+    err.is::<NotFound>().then_some(EXIT_NOT_FOUND)
+}
+
 /// `ls` subcommand
 #[derive(clap::Parser, Command, Debug)]
+// `-R` is added as short option to `--recursive` of the flattened list options
+#[clap(
+    mut_arg("recursive", |arg| arg.short('R')),
+    after_help = "Exit codes: 0 if at least one entry was listed, 1 on errors, 2 if the snapshot or path (all snapshots if several are given) was not found, 3 if no entry was listed (empty dir or all entries filtered out; no matching snapshot when listing snapshots)"
+)]
 pub(crate) struct LsCmd {
-    /// Snapshot/path to list
+    /// Snapshots/paths to list (use "latest" for the newest snapshot matching the snapshot filter options; of several snapshots with the same time, the one with the largest id is taken). To only list paths matching a pattern, use --glob (e.g. --glob '**/*.rs'). Without arguments, the snapshots matching the snapshot filter options are listed
     #[clap(value_name = "SNAPSHOT[:PATH]")]
-    snap: String,
+    args: Vec<String>,
+
+    /// don't list paths matching the pattern, same as --glob '!PATTERN' (can be specified multiple times)
+    #[clap(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// don't list paths matching the patterns read from the given file, one per line (same as --exclude for each pattern; empty lines and lines starting with # are ignored)
+    #[clap(long, value_name = "FILE")]
+    exclude_file: Option<PathBuf>,
+
+    /// only list entries of the given type: f (file), d (dir) or l (symlink) (can be specified multiple times)
+    #[clap(long = "type", value_enum, value_name = "TYPE")]
+    types: Vec<LsType>,
+
+    /// only list entries with at least the given size (e.g. "100M")
+    #[clap(long, value_name = "SIZE")]
+    min_size: Option<ByteSize>,
+
+    /// only list entries with at most the given size (e.g. "1G")
+    #[clap(long, value_name = "SIZE")]
+    max_size: Option<ByteSize>,
 
-    /// show summary
-    #[clap(long, short = 's', conflicts_with = "json")]
+    /// only list entries modified after the given date/time or duration ago (e.g. "2024-01-01" or "30d"; duration units: s, m, h, d, w, mo, y)
+    #[clap(long, value_name = "DATE(TIME)|DURATION")]
+    newer_than: Option<LsTime>,
+
+    /// only list entries modified before the given date/time or duration ago (e.g. "2024-01-01" or "3mo"; duration units: s, m, h, d, w, mo, y)
+    #[clap(long, value_name = "DATE(TIME)|DURATION")]
+    older_than: Option<LsTime>,
+
+    /// show summary (this is the default except for --null; with --json a summary object is printed after the list)
+    #[clap(long, short = 's')]
     summary: bool,
 
+    /// don't show the summary of the listing
+    #[clap(long, conflicts_with = "summary")]
+    no_summary: bool,
+
     /// show long listing
     #[clap(long, short = 'l', conflicts_with = "json")]
     long: bool,
 
     /// show listing in json
-    #[clap(long, conflicts_with_all = ["summary", "long"])]
+    #[clap(long, conflicts_with = "long")]
     json: bool,
 
+    /// show listing as an indented tree (can be combined with --long)
+    #[clap(long, conflicts_with_all = ["json", "json_lines", "events", "stat_only"])]
+    tree: bool,
+
+    /// terminate each listed path by a NUL byte instead of a newline, printing the raw path (e.g. for `xargs -0`); the summary is only shown with --summary
+    #[clap(long, short = '0', conflicts_with_all = ["json", "json_lines", "long", "tree", "events", "stat_only", "count_only", "mime"])]
+    null: bool,
+
+    /// print each entry using the given template with placeholders {path}, {name}, {size}, {mode}, {user}, {group}, {uid}, {gid}, {mtime} and {type} (e.g. '{path}\t{size}'; use {{ and }} for braces and \t, \n for tab and newline)
+    #[clap(long, value_name = "TEMPLATE", conflicts_with_all = ["long", "json", "json_lines", "tree", "events", "stat_only", "count_only", "null"])]
+    format: Option<LsFormat>,
+
+    /// show listing as json object with the metadata of each entry, one per line
+    #[clap(long, conflicts_with_all = ["summary", "long", "json", "events", "stat_only"])]
+    json_lines: bool,
+
+    /// show sizes in human-readable format in the long listing and the summary
+    #[clap(long)]
+    human_readable: bool,
+
     /// show uid/gid instead of user/group
     #[clap(long, long("numeric-uid-gid"))]
     numeric_id: bool,
 
+    /// format of the modification time in the long listing: a strftime format string (e.g. "%Y-%m-%d %H:%M"), "relative" or "iso"
+    #[clap(long, value_name = "FORMAT")]
+    time_format: Option<TimeFormat>,
+
+    /// sort names case-insensitively
+    #[clap(long)]
+    ignore_case: bool,
+
+    /// sort all entries before printing (default: the order stored in the snapshot, which is streamed without collecting all entries)
+    #[clap(long, value_enum, value_name = "ORDER", default_value_t = LsSort::None)]
+    sort: LsSort,
+
+    /// print entries in reverse order (all entries are collected before printing)
+    #[clap(long)]
+    reverse: bool,
+
+    /// stop after printing the given number of entries
+    #[clap(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// show the content type of each entry, detected by its magic bytes
+    #[clap(long)]
+    mime: bool,
+
+    /// show the size of files as stored in the repository (compressed and encrypted) in an additional column of the long listing; with recursive listing also for dirs
+    #[clap(long, requires = "long")]
+    stored: bool,
+
+    /// only list entries with a detected content type matching the pattern (e.g. "image/*")
+    #[clap(long, value_name = "PATTERN")]
+    filter_mime: Option<String>,
+
+    /// only print the tree id and the number of direct child nodes
+    #[clap(long, conflicts_with_all = ["summary", "long"])]
+    stat_only: bool,
+
+    /// only print the summary of all matching entries instead of listing them (with --json as summary object)
+    #[clap(long, conflicts_with_all = ["long", "tree", "json_lines", "events", "stat_only", "no_summary"])]
+    count_only: bool,
+
+    /// emit a stream of json events (start, entry, error and end), one per line
+    #[clap(long, conflicts_with_all = ["summary", "long", "json", "stat_only"])]
+    events: bool,
+
     /// Listing options
     #[clap(flatten)]
     ls_opts: LsOptions,
 }
 
+/// Order of the entries listed by `ls`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LsSort {
+    /// sort by path
+    Path,
+    /// sort by size, then by path
+    Size,
+    /// sort by modification time, then by path
+    Mtime,
+    /// don't sort
+    None,
+}
+
+/// Type of entries listed by `ls --type`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LsType {
+    /// regular file
+    #[value(name = "f")]
+    File,
+    /// directory
+    #[value(name = "d")]
+    Dir,
+    /// symlink
+    #[value(name = "l")]
+    Symlink,
+}
+
+impl LsType {
+    /// Whether the node is of this type
+    fn matches(self, node: &Node) -> bool {
+        match self {
+            Self::File => node.is_file(),
+            Self::Dir => node.is_dir(),
+            Self::Symlink => node.is_symlink(),
+        }
+    }
+}
+
+/// The name of the type of `node` as used in the json listings and `--format`
+const fn node_type_name(node: &Node) -> &'static str {
+    match node.node_type {
+        NodeType::File => "file",
+        NodeType::Dir => "dir",
+        NodeType::Symlink { .. } => "symlink",
+        NodeType::Dev { .. } => "dev",
+        NodeType::Chardev { .. } => "chardev",
+        NodeType::Fifo => "fifo",
+        NodeType::Socket => "socket",
+    }
+}
+
+/// A field of an entry which can be used as placeholder in `ls --format`
+#[derive(Clone, Copy, Debug)]
+enum LsField {
+    Path,
+    Name,
+    Size,
+    Mode,
+    User,
+    Group,
+    Uid,
+    Gid,
+    Mtime,
+    Type,
+}
+
+impl FromStr for LsField {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "path" => Self::Path,
+            "name" => Self::Name,
+            "size" => Self::Size,
+            "mode" => Self::Mode,
+            "user" => Self::User,
+            "group" => Self::Group,
+            "uid" => Self::Uid,
+            "gid" => Self::Gid,
+            "mtime" => Self::Mtime,
+            "type" => Self::Type,
+            _ => bail!(
+                "unknown placeholder {{{s}}}, available are {{path}}, {{name}}, {{size}}, {{mode}}, \
+                 {{user}}, {{group}}, {{uid}}, {{gid}}, {{mtime}} and {{type}}"
+            ),
+        })
+    }
+}
+
+/// A part of a `ls --format` template
+#[derive(Clone, Debug)]
+enum LsFormatPart {
+    Text(String),
+    Field(LsField),
+}
+
+/// A template given by `ls --format`, which is parsed up front to reject unknown placeholders
+#[derive(Clone, Debug)]
+struct LsFormat(Vec<LsFormatPart>);
+
+impl FromStr for LsFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("unclosed placeholder {{{name} in format"),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(LsFormatPart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(LsFormatPart::Field(name.parse()?));
+                }
+                '}' => bail!("unmatched }} in format, use }}}} for a literal }}"),
+                '\\' => match chars.next_if(|c| matches!(*c, 't' | 'n' | '0' | '\\')) {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('0') => text.push('\0'),
+                    _ => text.push('\\'),
+                },
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(LsFormatPart::Text(text));
+        }
+        Ok(Self(parts))
+    }
+}
+
+impl LsFormat {
+    /// Fill the template with the fields of `node` at `path`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the entry
+    /// * `node` - the node of the entry
+    /// * `human_readable` - whether to show the size in human-readable format
+    /// * `time_format` - the format of the modification time (default: ISO 8601)
+    /// * `zone` - the time zone of the modification time
+    fn format(
+        &self,
+        path: &Path,
+        node: &Node,
+        human_readable: bool,
+        time_format: Option<&TimeFormat>,
+        zone: DisplayTimeZone,
+    ) -> String {
+        let opt = |v: Option<String>| v.unwrap_or_else(|| "?".to_string());
+        let meta = &node.meta;
+        self.0
+            .iter()
+            .map(|part| match part {
+                LsFormatPart::Text(text) => text.clone(),
+                LsFormatPart::Field(field) => match field {
+                    LsField::Path => path.display().to_string(),
+                    LsField::Name => node.name().to_string_lossy().to_string(),
+                    LsField::Size if human_readable => bytes_size_to_string(meta.size),
+                    LsField::Size => meta.size.to_string(),
+                    LsField::Mode => node.mode_str(),
+                    LsField::User => opt(meta.user.clone()),
+                    LsField::Group => opt(meta.group.clone()),
+                    LsField::Uid => opt(meta.uid.map(|uid| uid.to_string())),
+                    LsField::Gid => opt(meta.gid.map(|gid| gid.to_string())),
+                    LsField::Mtime => opt(meta
+                        .mtime
+                        .map(|t| time_format.unwrap_or(&TimeFormat::Iso).format(&t, zone))),
+                    LsField::Type => node_type_name(node).to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// A point in time given as date/time or as duration before now
+#[derive(Clone, Copy, Debug)]
+struct LsTime(DateTime<Local>);
+
+impl FromStr for LsTime {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let duration_err = match s.parse::<RelativeDuration>() {
+            Ok(duration) => return Ok(Self(duration.before_now()?)),
+            Err(err) => err,
+        };
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        match dateparser::parse_with(s, &Local, midnight) {
+            Ok(datetime) => Ok(Self(datetime.into())),
+            Err(err) => {
+                bail!("{s:?} is neither a date/time ({err}) nor a duration ({duration_err})")
+            }
+        }
+    }
+}
+
 impl Runnable for LsCmd {
     fn run(&self) {
-        if let Err(err) = RUSTIC_APP
-            .config()
-            .repository
-            .run_indexed(|repo| self.inner_run(repo))
-        {
-            status_err!("{}", err);
-            RUSTIC_APP.shutdown(Shutdown::Crash);
+        let repository = &RUSTIC_APP.config().repository;
+        let result = if self.args.is_empty() {
+            repository.run_open(|repo| self.list_snapshots(&repo))
+        } else {
+            repository.run_indexed(|repo| self.inner_run(repo))
+        };
+        match result {
+            Ok(true) => {}
+            Ok(false) => RUSTIC_APP.shutdown_with_exitcode(Shutdown::Graceful, EXIT_NO_ENTRIES),
+            Err(err) => {
+                if self.events {
+                    LsEvent::Error {
+                        message: format!("{err:#}"),
+                    }
+                    .emit();
+                } else {
+                    status_err!("{}", err);
+                }
+                if let Some(code) = error_exit_code(&err) {
+                    RUSTIC_APP.shutdown_with_exitcode(Shutdown::Crash, code);
+                }
+                RUSTIC_APP.shutdown(Shutdown::Crash);
+            }
         };
     }
 }
@@ -75,7 +430,7 @@ impl Runnable for LsCmd {
 /// Summary of a ls command
 ///
 /// This struct is used to print a summary of the ls command.
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Serialize)]
 pub struct Summary {
     pub files: usize,
     pub size: u64,
@@ -119,6 +474,8 @@ impl Summary {
 pub trait NodeLs {
     fn mode_str(&self) -> String;
     fn link_str(&self) -> String;
+    fn type_str(&self) -> String;
+    fn device_str(&self) -> Option<String>;
 }
 
 impl NodeLs for Node {
@@ -146,54 +503,789 @@ impl NodeLs for Node {
             String::new()
         }
     }
+    fn type_str(&self) -> String {
+        match &self.node_type {
+            NodeType::File => "file".to_string(),
+            NodeType::Dir => "directory".to_string(),
+            NodeType::Symlink { .. } => "symlink".to_string(),
+            NodeType::Dev { device } => format!("block device {}", format_device(*device)),
+            NodeType::Chardev { device } => format!("char device {}", format_device(*device)),
+            NodeType::Fifo => "fifo".to_string(),
+            NodeType::Socket => "socket".to_string(),
+        }
+    }
+    fn device_str(&self) -> Option<String> {
+        match self.node_type {
+            NodeType::Dev { device } | NodeType::Chardev { device } => Some(format_device(device)),
+            _ => None,
+        }
+    }
+}
+
+/// Format a device id as `major:minor`
+fn format_device(device: u64) -> String {
+    let (major, minor) = device_numbers(device);
+    format!("{major}:{minor}")
+}
+
+/// Split a device id into major and minor number (using the encoding of glibc)
+pub const fn device_numbers(device: u64) -> (u64, u64) {
+    let major = ((device >> 8) & 0xfff) | ((device >> 32) & !0xfff);
+    let minor = (device & 0xff) | ((device >> 12) & !0xff);
+    (major, minor)
 }
 
 impl LsCmd {
-    fn inner_run(&self, repo: CliIndexedRepo) -> Result<()> {
+    /// The criteria selecting the entries to list
+    fn selection(&self) -> Result<LsSelection> {
+        let mut ls_opts = self.ls_opts.clone();
+        // excludes are used like negated --glob; they are added last to take precedence
+        let file_excludes = self
+            .exclude_file
+            .as_deref()
+            .map(read_exclude_file)
+            .transpose()?
+            .unwrap_or_default();
+        ls_opts.glob.extend(
+            self.exclude
+                .iter()
+                .chain(&file_excludes)
+                .map(|pattern| format!("!{pattern}")),
+        );
+        Ok(LsSelection {
+            ls_opts,
+            types: self.types.clone(),
+            min_size: self.min_size.map(|size| size.0),
+            max_size: self.max_size.map(|size| size.0),
+            newer_than: self.newer_than.map(|time| time.0),
+            older_than: self.older_than.map(|time| time.0),
+            mime: self.mime,
+            filter_mime: self.filter_mime.clone(),
+            // the order doesn't matter if only counting
+            sort: if self.count_only {
+                LsSort::None
+            } else {
+                self.sort
+            },
+            reverse: self.reverse && !self.count_only,
+            ignore_case: self.ignore_case && !self.count_only,
+        })
+    }
+
+    /// Whether to show the summary after listing the entries
+    ///
+    /// It is shown unless suppressed, but only on request for NUL-terminated output to keep it parseable.
+    const fn show_summary(&self) -> bool {
+        !self.no_summary && (!self.null || self.summary)
+    }
+
+    /// Print the listed entries as an indented tree
+    ///
+    /// Entries are printed below their parent entry; entries whose parent is not listed (e.g. due to filtering)
+    /// are printed at the top level with their full path.
+    fn print_tree(&self, entries: &[(PathBuf, Node, Option<&str>)], zone: DisplayTimeZone) {
+        let paths: BTreeSet<&Path> = entries.iter().map(|(path, _, _)| path.as_path()).collect();
+        let mut children: BTreeMap<Option<&Path>, Vec<usize>> = BTreeMap::new();
+        for (idx, (path, _, _)) in entries.iter().enumerate() {
+            let parent = path.parent().filter(|parent| paths.contains(parent));
+            children.entry(parent).or_default().push(idx);
+        }
+        let connectors = TreeConnectors::for_stdout();
+        // all entries are known, so the columns can be fitted to all of them
+        let mut widths = LongWidths::default();
+        if self.long {
+            for (_, node, _) in entries {
+                widths.fit(&long_columns(
+                    node,
+                    self.numeric_id,
+                    self.human_readable,
+                    self.time_format.as_ref(),
+                    zone,
+                ));
+            }
+        }
+        let mut stack: Vec<(usize, String, bool)> = children
+            .get(&None)
+            .into_iter()
+            .flatten()
+            .rev()
+            .map(|idx| (*idx, String::new(), true))
+            .collect();
+        // depth-first traversal; `prefix` is the indentation inherited from the parents
+        while let Some((idx, prefix, is_root)) = stack.pop() {
+            let (path, node, mime) = &entries[idx];
+            let (connector, name, child_prefix) = if is_root {
+                (String::new(), path.as_os_str(), String::new())
+            } else {
+                let is_last = path
+                    .parent()
+                    .and_then(|parent| children.get(&Some(parent)))
+                    .and_then(|siblings| siblings.last())
+                    == Some(&idx);
+                let (connector, indent) = if is_last {
+                    (connectors.last, connectors.blank)
+                } else {
+                    (connectors.entry, connectors.line)
+                };
+                (
+                    format!("{prefix}{connector}"),
+                    path.file_name().unwrap_or_default(),
+                    format!("{prefix}{indent}"),
+                )
+            };
+            let name = name.to_string_lossy();
+            let suffix = if node.is_dir() { "/" } else { "" };
+            if self.mime {
+                print!("{:<26} ", mime.unwrap_or_default());
+            }
+            if self.long {
+                print!(
+                    "{} ",
+                    node_columns(
+                        node,
+                        &mut widths,
+                        self.numeric_id,
+                        self.human_readable,
+                        self.time_format.as_ref(),
+                        zone,
+                    )
+                );
+            }
+            let link = node.link_str();
+            if link.is_empty() {
+                println!("{connector}{name}{suffix}");
+            } else {
+                println!("{connector}{name}{suffix} {link}");
+            }
+            if let Some(child_idxs) = children.get(&Some(path.as_path())) {
+                stack.extend(
+                    child_idxs
+                        .iter()
+                        .rev()
+                        .map(|idx| (*idx, child_prefix.clone(), false)),
+                );
+            }
+        }
+    }
+
+    /// List the snapshots matching the snapshot filter options, used if no snapshot is given
+    ///
+    /// Returns whether at least one snapshot was listed.
+    fn list_snapshots(&self, repo: &CliOpenRepo) -> Result<bool> {
         let config = RUSTIC_APP.config();
+        let zone = config.global.time_zone();
+        let mut snapshots = repo.get_matching_snapshots(|sn| config.snapshot_filter.matches(sn))?;
+        snapshots.sort_unstable();
+        if self.json {
+            serde_json::to_writer_pretty(io::stdout(), &snapshots)?;
+            println!();
+        } else {
+            let mut table = table_with_titles(["ID", "Time", "Host", "Tags", "Paths"]);
+            _ = table.add_rows(snapshots.iter().map(|sn| {
+                [
+                    sn.id.to_string(),
+                    zone.format(&sn.time, "%Y-%m-%d %H:%M:%S"),
+                    sn.hostname.clone(),
+                    sn.tags.formatln(),
+                    sn.paths.formatln(),
+                ]
+            }));
+            println!("{table}");
+            println!("{} snapshot(s)", snapshots.len());
+        }
+        Ok(!snapshots.is_empty())
+    }
 
-        let node =
-            repo.node_from_snapshot_path(&self.snap, |sn| config.snapshot_filter.matches(sn))?;
+    /// List all given snapshots, each with a header line if there are several
+    ///
+    /// Snapshots which are not found are skipped with a warning.
+    /// Returns whether at least one entry was listed.
+    fn inner_run(&self, repo: CliIndexedRepo) -> Result<bool> {
+        let selection = self.selection()?;
+        if let [snap] = self.args.as_slice() {
+            return self.list(&repo, snap, &selection);
+        }
+        if self.json {
+            bail!("--json can only be used when listing a single snapshot");
+        }
+        let with_header = !self.null && !self.json_lines && !self.events;
+        let mut listed = false;
+        let mut found = false;
+        for (i, snap) in self.args.iter().enumerate() {
+            if with_header {
+                if i > 0 {
+                    println!();
+                }
+                println!("==> {snap} <==");
+            }
+            match self.list(&repo, snap, &selection) {
+                Ok(entries) => {
+                    listed |= entries;
+                    found = true;
+                }
+                Err(err) if err.is::<NotFound>() => warn!("skipping {snap}: {err}"),
+                Err(err) => return Err(err),
+            }
+        }
+        if !found {
+            return Err(NotFound("none of the given snapshots was found".to_string()).into());
+        }
+        Ok(listed)
+    }
 
-        // recursive if standard if we specify a snapshot without dirs. In other cases, use the parameter `recursive`
-        let mut ls_opts = self.ls_opts.clone();
-        ls_opts.recursive = !self.snap.contains(':') || ls_opts.recursive;
+    /// List the entries of the snapshot/path `snap_arg` selected by `selection`
+    ///
+    /// Returns whether at least one entry was listed.
+    fn list(&self, repo: &CliIndexedRepo, snap_arg: &str, selection: &LsSelection) -> Result<bool> {
+        let config = RUSTIC_APP.config();
+        let zone = config.global.time_zone();
+
+        let ResolvedNode {
+            snapshot,
+            path,
+            node,
+        } = resolve_node(repo, snap_arg, &config.snapshot_filter)?;
+
+        if self.stat_only {
+            let Some(tree_id) = node.subtree else {
+                bail!("{snap_arg} is not a directory");
+            };
+            let nodes = repo.get_tree(&tree_id)?.nodes.len();
+            if self.json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "tree_id": tree_id.to_hex().to_string(), "nodes": nodes })
+                );
+            } else {
+                println!("tree_id: {}", tree_id.to_hex());
+                println!("nodes: {nodes}");
+            }
+            return Ok(true);
+        }
 
         let mut summary = Summary::default();
 
-        if self.json {
+        if self.json && !self.count_only {
             print!("[");
         }
+        if self.events {
+            LsEvent::Start {
+                snapshot: &snapshot,
+                path: path.as_deref(),
+            }
+            .emit();
+        }
 
+        // show the progress on stderr only if it doesn't interfere with the listing, i.e. if the
+        // listing is not written to the terminal or only printed after all entries are collected
+        let collected = self.count_only
+            || self.sort != LsSort::None
+            || self.ignore_case
+            || self.reverse
+            || self.tree;
+        let p = if io::stderr().is_terminal() && (collected || !io::stdout().is_terminal()) {
+            repo.progress_bars().progress_counter("listing entries...")
+        } else {
+            repo.progress_bars().progress_hidden()
+        };
+        let listing = list_node(repo, &node, selection, p.clone())?;
         let mut first_item = true;
-        for item in repo.ls(&node, &ls_opts)? {
-            let (path, node) = item?;
+        let mut count = 0;
+        let mut truncated = false;
+        let mut tree_entries = Vec::new();
+        let mut stored_sizes = StoredSizes::default();
+        let mut widths = LongWidths::default();
+        for item in listing {
+            let LsItem { path, node, mime } = match item {
+                Ok(item) => item,
+                Err(err) if self.events => {
+                    LsEvent::Error {
+                        message: err.to_string(),
+                    }
+                    .emit();
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if self.limit.is_some_and(|limit| count >= limit) {
+                truncated = true;
+                break;
+            }
+            count += 1;
             summary.update(&node);
-            if self.json {
+            if self.count_only {
+                first_item = false;
+                continue;
+            }
+            if self.mime
+                && !self.json
+                && !self.events
+                && !self.json_lines
+                && !self.tree
+                && self.format.is_none()
+            {
+                print!("{:<26} ", mime.unwrap_or_default());
+            }
+            if self.events {
+                LsEvent::Entry {
+                    path: &path,
+                    node: &node,
+                    mime,
+                }
+                .emit();
+            } else if self.tree {
+                tree_entries.push((path, node, mime));
+            } else if self.json_lines {
+                println!("{}", serde_json::to_string(&LsEntry::new(&path, &node))?);
+            } else if self.json {
                 if !first_item {
                     print!(",");
                 }
                 print!("{}", serde_json::to_string(&path)?);
+            } else if let Some(format) = &self.format {
+                println!(
+                    "{}",
+                    format.format(
+                        &path,
+                        &node,
+                        self.human_readable,
+                        self.time_format.as_ref(),
+                        zone,
+                    )
+                );
             } else if self.long {
-                print_node(&node, &path, self.numeric_id);
+                if self.stored {
+                    let size = stored_sizes.node(repo, &node, selection.ls_opts.recursive)?;
+                    let size = size.map_or_else(
+                        || "-".to_string(),
+                        |size| {
+                            if self.human_readable {
+                                bytes_size_to_string(size)
+                            } else {
+                                size.to_string()
+                            }
+                        },
+                    );
+                    print!("{size:>9} ");
+                }
+                print_node(
+                    &node,
+                    &path,
+                    &mut widths,
+                    self.numeric_id,
+                    self.human_readable,
+                    self.time_format.as_ref(),
+                    zone,
+                );
+            } else if self.null {
+                write_null_terminated(&mut io::stdout().lock(), &path)?;
             } else {
                 println!("{}", path.display());
             }
             first_item = false;
         }
+        p.finish();
+        if truncated {
+            info!("output limited to {count} entries (--limit)");
+        }
+
+        if self.tree {
+            self.print_tree(&tree_entries, zone);
+        }
 
         if self.json {
-            println!("]");
+            if !self.count_only {
+                println!("]");
+            }
+            if self.show_summary() {
+                println!("{}", serde_json::to_string(&summary)?);
+            }
+        }
+        if self.events {
+            LsEvent::End { summary }.emit();
         }
 
-        if self.summary {
+        if self.show_summary() && !self.json && !self.events && !self.json_lines {
+            let size = if self.human_readable {
+                bytes_size_to_string(summary.size)
+            } else {
+                format!("{} bytes", summary.size)
+            };
             println!(
-                "total: {} dirs, {} files, {} bytes",
-                summary.dirs, summary.files, summary.size
+                "{} files, {} dirs, {size} total",
+                summary.files, summary.dirs
+            );
+        }
+
+        Ok(!first_item)
+    }
+}
+
+/// An entry printed by `ls --json-lines`
+///
+/// Missing metadata is serialized as `null` to keep the schema stable.
+#[derive(Serialize)]
+struct LsEntry<'a> {
+    path: &'a Path,
+    #[serde(rename = "type")]
+    node_type: &'static str,
+    size: u64,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    user: Option<&'a str>,
+    group: Option<&'a str>,
+    mtime: Option<DateTime<Local>>,
+}
+
+impl<'a> LsEntry<'a> {
+    fn new(path: &'a Path, node: &'a Node) -> Self {
+        let meta = &node.meta;
+        Self {
+            path,
+            node_type: node_type_name(node),
+            size: meta.size,
+            mode: meta.mode,
+            uid: meta.uid,
+            gid: meta.gid,
+            user: meta.user.as_deref(),
+            group: meta.group.as_deref(),
+            mtime: meta.mtime,
+        }
+    }
+}
+
+/// An event emitted by `ls --events`
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum LsEvent<'a> {
+    /// listing started
+    Start {
+        snapshot: &'a SnapshotFile,
+        path: Option<&'a str>,
+    },
+    /// a listed entry
+    Entry {
+        path: &'a Path,
+        node: &'a Node,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mime: Option<&'a str>,
+    },
+    /// an error occurred
+    Error { message: String },
+    /// listing finished
+    End { summary: Summary },
+}
+
+impl LsEvent<'_> {
+    /// Print the event as a single json line
+    fn emit(&self) {
+        match serde_json::to_string(self) {
+            Ok(line) => println!("{line}"),
+            Err(err) => status_err!("cannot serialize event: {}", err),
+        }
+    }
+}
+
+/// Criteria selecting the entries to list, independent of how they are printed
+pub struct LsSelection {
+    /// options for traversing the tree, including the glob patterns selecting paths
+    pub ls_opts: LsOptions,
+    /// only list entries of one of these types (all types if empty)
+    pub types: Vec<LsType>,
+    /// only list entries of at least this size
+    pub min_size: Option<u64>,
+    /// only list entries of at most this size
+    pub max_size: Option<u64>,
+    /// only list entries modified after this time
+    pub newer_than: Option<DateTime<Local>>,
+    /// only list entries modified before this time
+    pub older_than: Option<DateTime<Local>>,
+    /// detect the content type of the listed entries
+    pub mime: bool,
+    /// only list entries whose content type matches this pattern (e.g. "image/*")
+    pub filter_mime: Option<String>,
+    /// the order of the listed entries
+    pub sort: LsSort,
+    /// list the entries in reverse order
+    pub reverse: bool,
+    /// compare paths case-insensitively when sorting
+    pub ignore_case: bool,
+}
+
+impl LsSelection {
+    /// Whether the node matches the type, size and modification time filters
+    ///
+    /// Nodes with unknown modification time don't match if a time filter is given.
+    fn matches_node(&self, node: &Node) -> bool {
+        let size = node.meta.size;
+        let mtime = node.meta.mtime;
+        (self.types.is_empty() || self.types.iter().any(|tpe| tpe.matches(node)))
+            && !matches!(self.min_size, Some(min) if size < min)
+            && !matches!(self.max_size, Some(max) if size > max)
+            && self
+                .newer_than
+                .is_none_or(|newer| mtime.is_some_and(|mtime| mtime > newer))
+            && self
+                .older_than
+                .is_none_or(|older| mtime.is_some_and(|mtime| mtime < older))
+    }
+}
+
+/// An entry selected for listing
+pub struct LsItem {
+    /// path relative to the listed node
+    pub path: PathBuf,
+    pub node: Node,
+    /// content type, if detected
+    pub mime: Option<&'static str>,
+}
+
+/// The entries of a tree selected by a [`LsSelection`], see [`list_node`]
+pub struct Listing<'a, I> {
+    repo: &'a CliIndexedRepo,
+    selection: &'a LsSelection,
+    items: I,
+    mime_cache: MimeCache,
+}
+
+impl<I: Iterator<Item = RusticResult<(PathBuf, Node)>>> Iterator for Listing<'_, I> {
+    type Item = Result<LsItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.items.by_ref() {
+            let (path, node) = match item {
+                Ok(item) => item,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if !self.selection.matches_node(&node) {
+                continue;
+            }
+            let mime = if self.selection.mime || self.selection.filter_mime.is_some() {
+                match self.mime_cache.get(self.repo, &node) {
+                    Ok(mime) => Some(mime),
+                    Err(err) => return Some(Err(err)),
+                }
+            } else {
+                None
+            };
+            if self
+                .selection
+                .filter_mime
+                .as_deref()
+                .zip(mime)
+                .is_some_and(|(pattern, mime)| !mime_matches(pattern, mime))
+            {
+                continue;
+            }
+            return Some(Ok(LsItem { path, node, mime }));
+        }
+        None
+    }
+}
+
+/// A node resolved from a `SNAPSHOT[:PATH]` argument, see [`resolve_node`]
+pub struct ResolvedNode {
+    pub snapshot: SnapshotFile,
+    /// the path within the snapshot, if given
+    pub path: Option<String>,
+    pub node: Node,
+}
+
+/// Resolve the snapshot/path `snap` into the snapshot and the node
+///
+/// For "latest", the newest snapshot matching `filter` is chosen, see [`latest_snapshot`].
+///
+/// # Arguments
+///
+/// * `repo` - the repository
+/// * `snap` - the snapshot/path, e.g. "latest:/etc" or "~label"
+/// * `filter` - the snapshot filter used for "latest" and "~"
+///
+/// # Errors
+///
+/// A [`NotFound`] error if no snapshot matches or the path doesn't exist in the snapshot; other errors
+/// (e.g. of the backend or for ambiguous snapshot ids) are passed through.
+pub fn resolve_node(
+    repo: &CliIndexedRepo,
+    snap: &str,
+    filter: &SnapshotFilter,
+) -> Result<ResolvedNode> {
+    let snap = resolve_snapshot_description(repo, snap, |sn| filter.matches(sn))?;
+    check_snapshot_prefix(repo, &snap)?;
+    let (id, path) = snap
+        .split_once(':')
+        .map_or((snap.as_str(), None), |(id, path)| (id, Some(path)));
+    let snapshot = if id == "latest" {
+        latest_snapshot(repo.get_matching_snapshots(|sn| filter.matches(sn))?).ok_or_else(|| {
+            NotFound("no snapshot matches the snapshot filter options".to_string())
+        })?
+    } else {
+        repo.get_snapshot_from_str(id, |sn| filter.matches(sn))?
+    };
+    let node_path = path.unwrap_or_default();
+    let node = match repo.node_from_snapshot_and_path(&snapshot, node_path) {
+        Ok(node) => node,
+        Err(err) if !path_exists(repo, snapshot.tree, node_path)? => {
+            return Err(NotFound(format!(
+                "{node_path} not found in snapshot {}: {err}",
+                snapshot.id
+            ))
+            .into());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    Ok(ResolvedNode {
+        path: path.map(str::to_string),
+        snapshot,
+        node,
+    })
+}
+
+/// The latest of the given snapshots
+///
+/// Snapshots are ordered by time. If several snapshots have the same (newest) time, the one with the
+/// largest id is chosen, so the result doesn't depend on the order in which the snapshots were read.
+fn latest_snapshot(snapshots: Vec<SnapshotFile>) -> Option<SnapshotFile> {
+    snapshots
+        .into_iter()
+        .max_by(|sn1, sn2| sn1.time.cmp(&sn2.time).then_with(|| sn1.id.cmp(&sn2.id)))
+}
+
+/// Whether `path` exists in the tree `tree_id`; errors reading the trees are passed through
+fn path_exists(repo: &CliIndexedRepo, tree_id: TreeId, path: &str) -> Result<bool> {
+    let mut tree_id = Some(tree_id);
+    for component in Path::new(path).components() {
+        let Component::Normal(name) = component else {
+            continue;
+        };
+        // only dirs have children
+        let Some(id) = tree_id else {
+            return Ok(false);
+        };
+        let tree = repo.get_tree(&id)?;
+        let Some(node) = tree
+            .nodes
+            .iter()
+            .find(|node| node.name().as_os_str() == name)
+        else {
+            return Ok(false);
+        };
+        tree_id = node.subtree;
+    }
+    Ok(true)
+}
+
+/// List the entries of `node` selected by `selection`
+///
+/// Unless sorting (or reversing) is requested, entries are read from the repository while iterating.
+/// Each entry read is counted by the progress `p`.
+///
+/// # Arguments
+///
+/// * `repo` - the repository
+/// * `node` - the node to list; a dir is traversed, a single file is listed with its name
+/// * `selection` - the criteria selecting the entries
+/// * `p` - the progress counting the entries read
+pub fn list_node<'a>(
+    repo: &'a CliIndexedRepo,
+    node: &Node,
+    selection: &'a LsSelection,
+    p: impl Progress + 'a,
+) -> Result<Listing<'a, impl Iterator<Item = RusticResult<(PathBuf, Node)>> + 'a>> {
+    let ls = if node.is_dir() {
+        Either::Left(repo.ls(node, &selection.ls_opts)?)
+    } else {
+        // a single file is listed with its name
+        Either::Right(std::iter::once(Ok((
+            PathBuf::from(node.name()),
+            node.clone(),
+        ))))
+    };
+    let ls = ls.inspect(move |_| p.inc(1));
+    // sorting case-insensitively only changes the order of paths
+    let sort = match selection.sort {
+        LsSort::None if selection.ignore_case => LsSort::Path,
+        sort => sort,
+    };
+    let items = if sort == LsSort::None && !selection.reverse {
+        Either::Right(ls)
+    } else {
+        let mut items: Vec<(PathBuf, Node)> = ls.collect::<RusticResult<_>>()?;
+        if sort != LsSort::None {
+            items.sort_by(|(path1, node1), (path2, node2)| {
+                match sort {
+                    LsSort::Size => node1.meta.size.cmp(&node2.meta.size),
+                    LsSort::Mtime => node1.meta.mtime.cmp(&node2.meta.mtime),
+                    LsSort::Path | LsSort::None => Ordering::Equal,
+                }
+                .then_with(|| cmp_paths(path1, path2, selection.ignore_case))
+            });
+        }
+        if selection.reverse {
+            items.reverse();
+        }
+        Either::Left(items.into_iter().map(Ok))
+    };
+    Ok(Listing {
+        repo,
+        selection,
+        items,
+        mime_cache: MimeCache::default(),
+    })
+}
+
+/// Read the exclude patterns from `file`, skipping empty lines and comments
+///
+/// The patterns have the same syntax as the ones given by `--glob`, but without a leading `!`,
+/// as they are used to exclude paths.
+fn read_exclude_file(file: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(file)
+        .map_err(|err| anyhow!("cannot read exclude file {}: {err}", file.display()))?;
+    let mut patterns = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('!') {
+            bail!(
+                "re-including paths ({line}) is not supported in exclude file {}, use --glob-file instead",
+                file.display()
             );
         }
+        patterns.push(line.to_string());
+    }
+    Ok(patterns)
+}
 
-        Ok(())
+/// Compare two names, optionally ignoring case
+///
+/// Names which only differ in case are ordered case-sensitively to get a stable order.
+pub fn cmp_names(name1: &OsStr, name2: &OsStr, ignore_case: bool) -> Ordering {
+    if ignore_case {
+        name1
+            .to_string_lossy()
+            .to_lowercase()
+            .cmp(&name2.to_string_lossy().to_lowercase())
+            .then_with(|| name1.cmp(name2))
+    } else {
+        name1.cmp(name2)
+    }
+}
+
+/// Compare two paths component-wise using [`cmp_names`]
+pub fn cmp_paths(path1: &Path, path2: &Path, ignore_case: bool) -> Ordering {
+    let (mut iter1, mut iter2) = (path1.iter(), path2.iter());
+    loop {
+        match (iter1.next(), iter2.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(name1), Some(name2)) => match cmp_names(name1, name2, ignore_case) {
+                Ordering::Equal => {}
+                ord => return ord,
+            },
+        }
     }
 }
 
@@ -203,9 +1295,162 @@ impl LsCmd {
 ///
 /// * `node` - the node to print
 /// * `path` - the path of the node
-pub fn print_node(node: &Node, path: &Path, numeric_uid_gid: bool) {
+/// * `widths` - the widths of the columns, which are widened to fit the node
+/// * `numeric_uid_gid` - whether to show uid/gid instead of user/group
+/// * `human_readable` - whether to show the size in human-readable format
+/// * `time_format` - the format of the modification time (default: like `ls -l`)
+/// * `zone` - the time zone of the modification time
+pub fn print_node(
+    node: &Node,
+    path: &Path,
+    widths: &mut LongWidths,
+    numeric_uid_gid: bool,
+    human_readable: bool,
+    time_format: Option<&TimeFormat>,
+    zone: DisplayTimeZone,
+) {
     println!(
-        "{:>10} {:>8} {:>8} {:>9} {:>17} {path:?} {}",
+        "{} {path:?} {}",
+        node_columns(
+            node,
+            widths,
+            numeric_uid_gid,
+            human_readable,
+            time_format,
+            zone
+        ),
+        node.link_str(),
+    );
+}
+
+/// The sizes of files and dirs as stored in the repository, caching the lookups in the index
+#[derive(Default)]
+struct StoredSizes {
+    blobs: HashMap<DataId, u64>,
+    trees: HashMap<TreeId, u64>,
+}
+
+impl StoredSizes {
+    /// The stored size of a data blob; blobs missing in the index count as 0
+    fn blob(&mut self, repo: &CliIndexedRepo, id: &DataId) -> u64 {
+        *self.blobs.entry(*id).or_insert_with(|| {
+            repo.get_index_entry(id)
+                .map_or(0, |entry| entry.length.into())
+        })
+    }
+
+    /// The sum of the stored sizes of the contents of a file
+    fn file(&mut self, repo: &CliIndexedRepo, node: &Node) -> u64 {
+        node.content
+            .iter()
+            .flatten()
+            .map(|id| self.blob(repo, id))
+            .sum()
+    }
+
+    /// The sum of the stored sizes of all files below the tree `id`
+    fn tree(&mut self, repo: &CliIndexedRepo, id: TreeId) -> Result<u64> {
+        if let Some(size) = self.trees.get(&id) {
+            return Ok(*size);
+        }
+        let mut size = 0;
+        for node in repo.get_tree(&id)?.nodes {
+            size += match node.subtree {
+                Some(subtree) => self.tree(repo, subtree)?,
+                None => self.file(repo, &node),
+            };
+        }
+        _ = self.trees.insert(id, size);
+        Ok(size)
+    }
+
+    /// The stored size of a node; for dirs, this is only computed if `with_dirs` is set
+    fn node(&mut self, repo: &CliIndexedRepo, node: &Node, with_dirs: bool) -> Result<Option<u64>> {
+        match node.subtree {
+            Some(id) if with_dirs => self.tree(repo, id).map(Some),
+            Some(_) => Ok(None),
+            None => Ok(Some(self.file(repo, node))),
+        }
+    }
+}
+
+/// Write the raw bytes of `path` terminated by a NUL byte
+fn write_null_terminated(out: &mut impl Write, path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    out.write_all(std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()))?;
+    #[cfg(not(unix))]
+    out.write_all(path.to_string_lossy().as_bytes())?;
+    out.write_all(b"\0")?;
+    Ok(())
+}
+
+/// Widths of the user, group, size and time columns of the long listing
+///
+/// The widths grow to fit the widest value seen so far, so while entries are streamed, the columns
+/// stay aligned as long as no wider value occurs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongWidths([usize; 4]);
+
+impl Default for LongWidths {
+    fn default() -> Self {
+        Self([8, 8, 9, 17])
+    }
+}
+
+impl LongWidths {
+    /// Widen the columns to fit the given columns of an entry
+    fn fit(&mut self, columns: &[String; 5]) {
+        // the mode column always has the same width
+        for (width, column) in self.0.iter_mut().zip(&columns[1..]) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+}
+
+/// The metadata columns of the long listing aligned to `widths`, which are widened to fit the node
+///
+/// # Arguments
+///
+/// * `node` - the node to format
+/// * `widths` - the widths of the columns
+/// * `numeric_uid_gid` - whether to show uid/gid instead of user/group
+/// * `human_readable` - whether to show the size in human-readable format
+/// * `time_format` - the format of the modification time (default: like `ls -l`)
+/// * `zone` - the time zone of the modification time
+fn node_columns(
+    node: &Node,
+    widths: &mut LongWidths,
+    numeric_uid_gid: bool,
+    human_readable: bool,
+    time_format: Option<&TimeFormat>,
+    zone: DisplayTimeZone,
+) -> String {
+    let columns = long_columns(node, numeric_uid_gid, human_readable, time_format, zone);
+    widths.fit(&columns);
+    let [mode, user, group, size, mtime] = columns;
+    let [user_width, group_width, size_width, mtime_width] = widths.0;
+    format!(
+        "{mode:>10} {user:>user_width$} {group:>group_width$} {size:>size_width$} {mtime:>mtime_width$}"
+    )
+}
+
+/// The metadata columns of the long listing: mode, user, group, size and modification time
+///
+/// # Arguments
+///
+/// * `node` - the node to format
+/// * `numeric_uid_gid` - whether to show uid/gid instead of user/group
+/// * `human_readable` - whether to show the size in human-readable format
+/// * `time_format` - the format of the modification time (default: like `ls -l`)
+/// * `zone` - the time zone of the modification time
+fn long_columns(
+    node: &Node,
+    numeric_uid_gid: bool,
+    human_readable: bool,
+    time_format: Option<&TimeFormat>,
+    zone: DisplayTimeZone,
+) -> [String; 5] {
+    [
         node.mode_str(),
         if numeric_uid_gid {
             node.meta.uid.map(|uid| uid.to_string())
@@ -219,13 +1464,67 @@ pub fn print_node(node: &Node, path: &Path, numeric_uid_gid: bool) {
             node.meta.group.clone()
         }
         .unwrap_or_else(|| "?".to_string()),
-        node.meta.size,
+        node.device_str().unwrap_or_else(|| {
+            if human_readable {
+                bytes_size_to_string(node.meta.size)
+            } else {
+                node.meta.size.to_string()
+            }
+        }),
         node.meta.mtime.map_or_else(
             || "?".to_string(),
-            |t| t.format("%_d %b %Y %H:%M").to_string()
+            |t| {
+                time_format.map_or_else(
+                    || zone.format(&t, "%_d %b %Y %H:%M"),
+                    |fmt| fmt.format(&t, zone),
+                )
+            },
         ),
-        node.link_str(),
-    );
+    ]
+}
+
+/// Connectors used to draw `ls --tree`
+#[derive(Clone, Copy)]
+struct TreeConnectors {
+    /// connector for an entry which has following siblings
+    entry: &'static str,
+    /// connector for the last entry of a dir
+    last: &'static str,
+    /// indentation below an entry which has following siblings
+    line: &'static str,
+    /// indentation below the last entry of a dir
+    blank: &'static str,
+}
+
+impl TreeConnectors {
+    const UNICODE: Self = Self {
+        entry: "├── ",
+        last: "└── ",
+        line: "│   ",
+        blank: "    ",
+    };
+    const ASCII: Self = Self {
+        entry: "|-- ",
+        last: "`-- ",
+        line: "|   ",
+        blank: "    ",
+    };
+
+    /// Box-drawing connectors if stdout is a UTF-8 terminal, else ASCII connectors
+    fn for_stdout() -> Self {
+        let utf8_locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+            .is_some_and(|value| {
+                let value = value.to_lowercase();
+                value.contains("utf-8") || value.contains("utf8")
+            });
+        if utf8_locale && io::stdout().is_terminal() {
+            Self::UNICODE
+        } else {
+            Self::ASCII
+        }
+    }
 }
 
 /// Convert permissions into readable format
@@ -261,3 +1560,206 @@ fn triplet(mode: u32, read: u32, write: u32, execute: u32) -> String {
     }
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+    use clap::Parser;
+    use rstest::rstest;
+    use rustic_core::repofile::Metadata;
+
+    /// A snapshot with the given time (in seconds) and an id consisting of the hex digit `id`
+    fn snapshot(secs: i64, id: char) -> SnapshotFile {
+        SnapshotFile {
+            time: Local.timestamp_opt(secs, 0).unwrap(),
+            id: serde_json::from_value(serde_json::json!(id.to_string().repeat(64))).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(&[(1, 'a')], Some('a'))]
+    #[case(&[(1, 'a'), (3, 'b'), (2, 'c')], Some('b'))]
+    #[case(&[(2, 'a'), (2, 'c'), (3, 'f'), (3, 'b')], Some('f'))]
+    #[case(&[(2, 'c'), (1, 'f'), (2, 'a')], Some('c'))]
+    fn latest_snapshot_passes(#[case] snapshots: &[(i64, char)], #[case] expected: Option<char>) {
+        let snapshots = snapshots
+            .iter()
+            .map(|(secs, id)| snapshot(*secs, *id))
+            .collect();
+        let expected = expected.map(|id| snapshot(0, id).id);
+        assert_eq!(latest_snapshot(snapshots).map(|sn| sn.id), expected);
+    }
+
+    /// A file node owned by `user` (uid 1000) in group "users" (gid 100) without modification time
+    fn file(user: &str, size: u64) -> Node {
+        let meta = Metadata {
+            mode: Some(0o644),
+            uid: Some(1000),
+            gid: Some(100),
+            user: Some(user.to_string()),
+            group: Some("users".to_string()),
+            size,
+            ..Default::default()
+        };
+        Node::new_node(OsStr::new("file"), NodeType::File, meta)
+    }
+
+    #[rstest]
+    #[case(false, false, ["-rw-r--r--", "alice", "users", "2048", "?"])]
+    #[case(true, false, ["-rw-r--r--", "1000", "100", "2048", "?"])]
+    #[case(false, true, ["-rw-r--r--", "alice", "users", "2.0 KiB", "?"])]
+    fn long_columns_passes(
+        #[case] numeric_uid_gid: bool,
+        #[case] human_readable: bool,
+        #[case] expected: [&str; 5],
+    ) {
+        let columns = long_columns(
+            &file("alice", 2048),
+            numeric_uid_gid,
+            human_readable,
+            None,
+            DisplayTimeZone::Local,
+        );
+        assert_eq!(columns, expected.map(str::to_string));
+    }
+
+    #[test]
+    fn node_columns_are_aligned_to_widest_value() {
+        let mut widths = LongWidths::default();
+        let row = node_columns(
+            &file("bob", 1),
+            &mut widths,
+            false,
+            false,
+            None,
+            DisplayTimeZone::Local,
+        );
+        assert_eq!(
+            row,
+            format!(
+                "-rw-r--r-- {:>8} {:>8} {:>9} {:>17}",
+                "bob", "users", 1, "?"
+            )
+        );
+        assert_eq!(widths, LongWidths::default());
+
+        let long_user = "a-rather-long-user-name";
+        let wide_row = node_columns(
+            &file(long_user, 1),
+            &mut widths,
+            false,
+            false,
+            None,
+            DisplayTimeZone::Local,
+        );
+        let row = node_columns(
+            &file("bob", 1),
+            &mut widths,
+            false,
+            false,
+            None,
+            DisplayTimeZone::Local,
+        );
+        assert_eq!(widths, LongWidths([long_user.len(), 8, 9, 17]));
+        assert_eq!(row.len(), wide_row.len());
+        assert!(row.contains(&format!(" {:>23} ", "bob")));
+    }
+
+    #[rstest]
+    #[case(&["ls", "snap"], &["snap"], false)]
+    #[case(&["ls", "latest:/home", "1a2b", "-R"], &["latest:/home", "1a2b"], true)]
+    #[case(&["ls", "--recursive", "a:/x", "b:/y", "c"], &["a:/x", "b:/y", "c"], true)]
+    #[case(&["ls"], &[], false)]
+    fn args_are_snapshots_passes(
+        #[case] cmdline: &[&str],
+        #[case] snapshots: &[&str],
+        #[case] recursive: bool,
+    ) {
+        let cmd = LsCmd::try_parse_from(cmdline).unwrap();
+        assert_eq!(cmd.args, snapshots);
+        assert_eq!(cmd.ls_opts.recursive, recursive);
+    }
+
+    #[rstest]
+    #[case(&["ls", "-0", "--long", "snap"])]
+    #[case(&["ls", "-0", "--mime", "snap"])]
+    #[case(&["ls", "--format", "{path}", "-0", "snap"])]
+    fn conflicting_args_fails(#[case] cmdline: &[&str]) {
+        assert!(LsCmd::try_parse_from(cmdline).is_err());
+    }
+
+    #[rstest]
+    #[case("{path}", "dir/file")]
+    #[case("{name} {size}", "file 2048")]
+    #[case("{user}:{group} {uid}:{gid}", "alice:users 1000:100")]
+    #[case("{type}\\t{mode}\\n", "file\t-rw-r--r--\n")]
+    #[case("{mtime}", "?")]
+    #[case("{{name}} }}{name}{{", "{name} }file{")]
+    #[case("a\\b\\\\c\\0", "a\\b\\c\0")]
+    fn ls_format_passes(#[case] template: &str, #[case] expected: &str) {
+        let format: LsFormat = template.parse().unwrap();
+        let line = format.format(
+            Path::new("dir/file"),
+            &file("alice", 2048),
+            false,
+            None,
+            DisplayTimeZone::Local,
+        );
+        assert_eq!(line, expected);
+    }
+
+    #[rstest]
+    #[case("{unknown}")]
+    #[case("{name")]
+    #[case("name}")]
+    #[case("{}")]
+    fn ls_format_fails(#[case] template: &str) {
+        assert!(template.parse::<LsFormat>().is_err());
+    }
+
+    #[rstest]
+    #[case(TreeConnectors::UNICODE)]
+    #[case(TreeConnectors::ASCII)]
+    fn tree_connectors_have_same_width(#[case] connectors: TreeConnectors) {
+        // all connectors must have the same width, else the tree is misaligned
+        for connector in [
+            connectors.entry,
+            connectors.last,
+            connectors.line,
+            connectors.blank,
+        ] {
+            assert_eq!(connector.chars().count(), 4, "{connector:?}");
+        }
+        assert!(connectors.blank.trim().is_empty());
+    }
+
+    #[rstest]
+    #[case(NotFound("no snapshot".to_string()).into(), Some(EXIT_NOT_FOUND))]
+    #[case(anyhow!("other error"), None)]
+    #[case(anyhow!(NotFound("no path".to_string())), Some(EXIT_NOT_FOUND))]
+    fn error_exit_code_passes(#[case] err: anyhow::Error, #[case] expected: Option<i32>) {
+        assert_eq!(error_exit_code(&err), expected);
+    }
+
+    #[rstest]
+    #[case("dir/file", b"dir/file\0")]
+    #[case("with space/and\nnewline", b"with space/and\nnewline\0")]
+    fn write_null_terminated_passes(#[case] path: &str, #[case] expected: &[u8]) {
+        let mut out = Vec::new();
+        write_null_terminated(&mut out, Path::new(path)).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_null_terminated_keeps_raw_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let mut out = Vec::new();
+        write_null_terminated(&mut out, Path::new(OsStr::from_bytes(b"a\xffb"))).unwrap();
+        assert_eq!(out, b"a\xffb\0");
+    }
+}
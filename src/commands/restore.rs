@@ -1,14 +1,27 @@
 //! `restore` subcommand
 
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
 use crate::{
-    Application, RUSTIC_APP, helpers::bytes_size_to_string, repository::CliIndexedRepo, status_err,
+    Application, RUSTIC_APP,
+    helpers::{bytes_size_to_string, resolve_snapshot_description},
+    repository::{CliIndexedRepo, throttle::RateLimiter},
+    status_err,
 };
 
 use abscissa_core::{Command, Runnable, Shutdown};
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
+use bytesize::ByteSize;
 use log::info;
+use serde::{Deserialize, Serialize};
 
-use rustic_core::{LocalDestination, LsOptions, RestoreOptions};
+use rustic_core::{LocalDestination, LsOptions, RestoreOptions, RusticResult, repofile::Node};
 
 use crate::filtering::SnapshotFilter;
 
@@ -21,13 +34,33 @@ pub(crate) struct RestoreCmd {
     snap: String,
 
     /// Restore destination
-    #[clap(value_name = "DESTINATION")]
-    dest: String,
+    #[clap(
+        value_name = "DESTINATION",
+        required_unless_present = "prefix",
+        conflicts_with = "prefix"
+    )]
+    dest: Option<String>,
+
+    /// Restore below the given existing directory, keeping the full snapshot path (e.g. /etc/fstab is restored to PREFIX/etc/fstab)
+    #[clap(long, value_name = "DIR")]
+    prefix: Option<PathBuf>,
 
     /// Restore options
     #[clap(flatten)]
     opts: RestoreOptions,
 
+    /// Limit the download rate from the repository (e.g. "10M" for 10MB/s)
+    #[clap(long, alias = "bwlimit", value_name = "RATE")]
+    limit_download: Option<ByteSize>,
+
+    /// Only write a manifest (JSON) of all entries to restore and the totals of the restore plan to the given file, without restoring
+    #[clap(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Only restore entries contained in the given manifest file
+    #[clap(long, value_name = "FILE")]
+    from_manifest: Option<PathBuf>,
+
     /// List options
     #[clap(flatten)]
     ls_opts: LsOptions,
@@ -41,10 +74,15 @@ pub(crate) struct RestoreCmd {
 }
 impl Runnable for RestoreCmd {
     fn run(&self) {
+        let limiter = self
+            .limit_download
+            .map(|rate| Arc::new(RateLimiter::new(rate.as_u64())));
         if let Err(err) = RUSTIC_APP
             .config()
             .repository
-            .run_indexed(|repo| self.inner_run(repo))
+            .run_indexed_with_limit(limiter.as_ref(), |repo| {
+                self.inner_run(repo, limiter.as_deref())
+            })
         {
             status_err!("{}", err);
             RUSTIC_APP.shutdown(Shutdown::Crash);
@@ -53,19 +91,41 @@ impl Runnable for RestoreCmd {
 }
 
 impl RestoreCmd {
-    fn inner_run(&self, repo: CliIndexedRepo) -> Result<()> {
+    fn inner_run(&self, repo: CliIndexedRepo, limiter: Option<&RateLimiter>) -> Result<()> {
         let config = RUSTIC_APP.config();
         let dry_run = config.global.dry_run;
 
-        let node =
-            repo.node_from_snapshot_path(&self.snap, |sn| config.snapshot_filter.matches(sn))?;
+        let snap = resolve_snapshot_description(&repo, &self.snap, |sn| {
+            config.snapshot_filter.matches(sn)
+        })?;
+        let node = repo.node_from_snapshot_path(&snap, |sn| config.snapshot_filter.matches(sn))?;
+        let dest_path = self.destination(&snap)?;
+        if self.prefix.is_some() {
+            println!("restoring to {dest_path}");
+        }
 
         // for restore, always recurse into tree
         let mut ls_opts = self.ls_opts.clone();
         ls_opts.recursive = true;
-        let ls = repo.ls(&node, &ls_opts)?;
 
-        let dest = LocalDestination::new(&self.dest, true, !node.is_dir())?;
+        let manifest_paths = self
+            .from_manifest
+            .as_deref()
+            .map(read_manifest)
+            .transpose()?;
+        let in_manifest = |item: &RusticResult<(PathBuf, Node)>| match (item, &manifest_paths) {
+            (Ok((path, _)), Some(paths)) => paths.contains(path),
+            _ => true,
+        };
+
+        if let Some(file) = &self.manifest {
+            let ls = repo.ls(&node, &ls_opts)?.filter(in_manifest);
+            return write_manifest(&repo, &self.opts, file, ls, &dest_path, node.is_dir());
+        }
+
+        let ls = repo.ls(&node, &ls_opts)?.filter(in_manifest);
+
+        let dest = LocalDestination::new(&dest_path, true, !node.is_dir())?;
 
         let restore_infos = repo.prepare_restore(&self.opts, ls, &dest, dry_run)?;
 
@@ -100,11 +160,192 @@ impl RestoreCmd {
             // save some memory
             let repo = repo.drop_data_from_index();
 
-            let ls = repo.ls(&node, &ls_opts)?;
+            let ls = repo.ls(&node, &ls_opts)?.filter(in_manifest);
             repo.restore(restore_infos, &self.opts, ls, &dest)?;
             println!("restore done.");
+            if self.prefix.is_some() {
+                println!("restored to {dest_path}");
+            }
+            if let Some(limiter) = limiter {
+                println!(
+                    "downloaded {} with an average rate of {}/s",
+                    bytes_size_to_string(limiter.bytes()),
+                    bytes_size_to_string(limiter.average_rate())
+                );
+            }
         }
 
         Ok(())
     }
+
+    /// The restore destination, which is the snapshot path remapped below `--prefix` if given
+    ///
+    /// # Arguments
+    ///
+    /// * `snap` - the (resolved) snapshot/path to restore
+    fn destination(&self, snap: &str) -> Result<String> {
+        let Some(prefix) = &self.prefix else {
+            return Ok(self.dest.clone().unwrap_or_default());
+        };
+        if !prefix.is_dir() {
+            bail!("prefix {} is not an existing directory", prefix.display());
+        }
+        let path = snap.split_once(':').map_or("", |(_, path)| path);
+        let dest = prefix.join(path.trim_start_matches('/'));
+        Ok(dest.to_string_lossy().to_string())
+    }
+}
+
+/// Write the manifest of all entries to restore
+///
+/// The restore of all entries is prepared once as dry-run and the totals of this plan are written
+/// to the manifest. `rustic_core` doesn't tell which action the plan takes for a single entry, so
+/// the entries only state whether their destination already exists.
+///
+/// # Arguments
+///
+/// * `repo` - the repository
+/// * `opts` - the restore options used to plan the restore
+/// * `file` - the manifest file to write
+/// * `ls` - the entries to restore
+/// * `dest` - the restore destination
+/// * `is_dir` - whether the restored node is a directory
+fn write_manifest(
+    repo: &CliIndexedRepo,
+    opts: &RestoreOptions,
+    file: &Path,
+    ls: impl Iterator<Item = RusticResult<(PathBuf, Node)>>,
+    dest: &str,
+    is_dir: bool,
+) -> Result<()> {
+    let nodes = ls.collect::<RusticResult<Vec<_>>>()?;
+    let destination = LocalDestination::new(dest, false, !is_dir)?;
+    let plan = repo.prepare_restore(opts, nodes.iter().cloned().map(Ok), &destination, true)?;
+
+    let entries: Vec<_> = nodes
+        .into_iter()
+        .map(|(path, node)| {
+            let dest = if is_dir {
+                Path::new(dest).join(&path)
+            } else {
+                PathBuf::from(dest)
+            };
+            let node_type = if node.is_dir() {
+                "dir"
+            } else if node.is_file() {
+                "file"
+            } else if node.is_symlink() {
+                "symlink"
+            } else {
+                "special"
+            };
+            ManifestEntry {
+                path,
+                node_type: node_type.to_string(),
+                size: node.meta.size,
+                exists: dest.symlink_metadata().is_ok(),
+            }
+        })
+        .collect();
+
+    let (files, dirs) = (plan.stats.files, plan.stats.dirs);
+    let manifest = Manifest {
+        plan: PlanTotals {
+            files: FileTotals {
+                restore: files.restore,
+                unchanged: files.unchanged,
+                verified: files.verified,
+                modify: files.modify,
+                additional: files.additional,
+            },
+            dirs: DirTotals {
+                restore: dirs.restore,
+                modify: dirs.modify,
+                additional: dirs.additional,
+            },
+            restore_size: plan.restore_size,
+            matched_size: plan.matched_size,
+        },
+        entries,
+    };
+
+    let writer = BufWriter::new(
+        File::create(file).with_context(|| format!("cannot create manifest {}", file.display()))?,
+    );
+    serde_json::to_writer_pretty(writer, &manifest)?;
+    info!(
+        "wrote manifest with {} entries to {}",
+        manifest.entries.len(),
+        file.display()
+    );
+    Ok(())
+}
+
+/// A restore manifest
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    /// totals of the restore plan for all entries, prepared as dry-run
+    plan: PlanTotals,
+    /// the entries to restore
+    entries: Vec<ManifestEntry>,
+}
+
+/// Totals of a restore plan
+#[derive(Serialize, Deserialize)]
+struct PlanTotals {
+    files: FileTotals,
+    dirs: DirTotals,
+    /// size of the file contents to restore
+    restore_size: u64,
+    /// size of the existing file contents which are used
+    matched_size: u64,
+}
+
+/// Number of files by the action planned for them
+#[derive(Serialize, Deserialize)]
+struct FileTotals {
+    restore: u64,
+    unchanged: u64,
+    verified: u64,
+    modify: u64,
+    additional: u64,
+}
+
+/// Number of dirs by the action planned for them
+#[derive(Serialize, Deserialize)]
+struct DirTotals {
+    restore: u64,
+    modify: u64,
+    additional: u64,
+}
+
+/// An entry of a restore manifest
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    /// path relative to the restored node
+    path: PathBuf,
+    /// type of the entry
+    #[serde(rename = "type")]
+    node_type: String,
+    /// size of the entry
+    size: u64,
+    /// whether the destination of the entry existed when writing the manifest
+    exists: bool,
+}
+
+/// Read the paths contained in a restore manifest
+///
+/// # Arguments
+///
+/// * `file` - the manifest file to read
+fn read_manifest(file: &Path) -> Result<BTreeSet<PathBuf>> {
+    let reader = BufReader::new(
+        File::open(file).with_context(|| format!("cannot open manifest {}", file.display()))?,
+    );
+    let manifest: Manifest = serde_json::from_reader(reader)?;
+    Ok(manifest
+        .entries
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect())
 }
@@ -2,7 +2,7 @@
 
 use crate::{
     Application, RUSTIC_APP,
-    helpers::{bold_cell, bytes_size_to_string, table, table_right_from},
+    helpers::{DisplayTimeZone, bold_cell, bytes_size_to_string, table, table_right_from},
     repository::CliOpenRepo,
     status_err,
 };
@@ -93,6 +93,7 @@ impl SnapshotCmd {
         }
 
         let config = RUSTIC_APP.config();
+        let zone = config.global.time_zone();
 
         let groups = repo.get_snapshot_group(&self.ids, self.group_by, |sn| {
             config.snapshot_filter.matches(sn)
@@ -130,7 +131,7 @@ impl SnapshotCmd {
                     let add_entry = |title: &str, value: String| {
                         _ = table.add_row([bold_cell(title), Cell::new(value)]);
                     };
-                    fill_table(&snap, add_entry);
+                    fill_table(&snap, zone, add_entry);
 
                     println!("{table}");
                     println!();
@@ -145,7 +146,7 @@ impl SnapshotCmd {
 
                 if self.all {
                     // Add all snapshots to output table
-                    _ = table.add_rows(snapshots.into_iter().map(|sn| snap_to_table(&sn, 0)));
+                    _ = table.add_rows(snapshots.into_iter().map(|sn| snap_to_table(&sn, 0, zone)));
                 } else {
                     // Group snapshts by treeid and output into table
                     _ = table.add_rows(
@@ -153,7 +154,7 @@ impl SnapshotCmd {
                             .into_iter()
                             .chunk_by(|sn| sn.tree)
                             .into_iter()
-                            .map(|(_, mut g)| snap_to_table(&g.next().unwrap(), g.count())),
+                            .map(|(_, mut g)| snap_to_table(&g.next().unwrap(), g.count(), zone)),
                     );
                 }
                 println!("{table}");
@@ -168,10 +169,10 @@ impl SnapshotCmd {
     }
 }
 
-pub fn snap_to_table(sn: &SnapshotFile, count: usize) -> [String; 9] {
+pub fn snap_to_table(sn: &SnapshotFile, count: usize, zone: DisplayTimeZone) -> [String; 9] {
     let tags = sn.tags.formatln();
     let paths = sn.paths.formatln();
-    let time = sn.time.format("%Y-%m-%d %H:%M:%S");
+    let time = zone.format(&sn.time, "%Y-%m-%d %H:%M:%S");
     let (files, dirs, size) = sn.summary.as_ref().map_or_else(
         || ("?".to_string(), "?".to_string(), "?".to_string()),
         |s| {
@@ -199,7 +200,11 @@ pub fn snap_to_table(sn: &SnapshotFile, count: usize) -> [String; 9] {
     ]
 }
 
-pub fn fill_table(snap: &SnapshotFile, mut add_entry: impl FnMut(&str, String)) {
+pub fn fill_table(
+    snap: &SnapshotFile,
+    zone: DisplayTimeZone,
+    mut add_entry: impl FnMut(&str, String),
+) {
     add_entry("Snapshot", snap.id.to_hex().to_string());
     // note that if original was not set, it is set to snap.id by the load process
     if let Some(original) = snap.original {
@@ -207,7 +212,7 @@ pub fn fill_table(snap: &SnapshotFile, mut add_entry: impl FnMut(&str, String))
             add_entry("Original ID", original.to_hex().to_string());
         }
     }
-    add_entry("Time", snap.time.format("%Y-%m-%d %H:%M:%S").to_string());
+    add_entry("Time", zone.format(&snap.time, "%Y-%m-%d %H:%M:%S"));
     add_entry("Generated by", snap.program_version.clone());
     add_entry("Host", snap.hostname.clone());
     add_entry("Label", snap.label.clone());
@@ -215,7 +220,7 @@ pub fn fill_table(snap: &SnapshotFile, mut add_entry: impl FnMut(&str, String))
     let delete = match snap.delete {
         DeleteOption::NotSet => "not set".to_string(),
         DeleteOption::Never => "never".to_string(),
-        DeleteOption::After(t) => format!("after {}", t.format("%Y-%m-%d %H:%M:%S")),
+        DeleteOption::After(t) => format!("after {}", zone.format(&t, "%Y-%m-%d %H:%M:%S")),
     };
     add_entry("Delete", delete);
     add_entry("Paths", snap.paths.formatln());
@@ -269,8 +274,8 @@ pub fn fill_table(snap: &SnapshotFile, mut add_entry: impl FnMut(&str, String))
         let duration = format!(
             "backup start: {} / backup end: {} / backup duration: {}\n\
             total duration: {}",
-            summary.backup_start.format("%Y-%m-%d %H:%M:%S"),
-            summary.backup_end.format("%Y-%m-%d %H:%M:%S"),
+            zone.format(&summary.backup_start, "%Y-%m-%d %H:%M:%S"),
+            zone.format(&summary.backup_end, "%Y-%m-%d %H:%M:%S"),
             format_duration(std::time::Duration::from_secs_f64(summary.backup_duration)),
             format_duration(std::time::Duration::from_secs_f64(summary.total_duration))
         );
@@ -5,26 +5,138 @@ mod progress;
 mod restore;
 mod snapshots;
 pub mod summary;
+mod tabs;
 mod tree;
 mod widgets;
 pub use diff::Diff;
 pub use snapshots::Snapshots;
 
-use std::io;
-use std::sync::{Arc, RwLock};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Result, bail};
+use base64::prelude::*;
+use bytesize::ByteSize;
+use conflate::Merge;
 use crossterm::event::{KeyEvent, KeyModifiers};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use log::debug;
 use progress::TuiProgressBars;
 use ratatui::prelude::*;
 use scopeguard::defer;
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
 use widgets::{Draw, ProcessEvent};
 
+use crate::helpers::{DEFAULT_CHUNK_SIZE, TimeFormat, clamp_chunk_size};
+
+/// Default maximum number of bytes shown when viewing files
+const DEFAULT_MAX_VIEW_SIZE: u64 = 1024 * 1024;
+
+/// Default number of trees of recently visited dirs kept in memory
+const DEFAULT_TREE_CACHE_SIZE: usize = 100;
+
+/// How often the UI is updated while jobs are running in the background
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Default format of the times shown in dir listings
+const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Options for the interactive UI
+#[serde_as]
+#[derive(Clone, Default, Debug, Serialize, Deserialize, Merge)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TuiOptions {
+    /// Number of bytes to request per read when viewing files [default: 1MiB]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[merge(strategy=conflate::option::overwrite_none)]
+    read_chunk_size: Option<ByteSize>,
+
+    /// Maximum number of bytes of a file shown when viewing files [default: 1MiB]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[merge(strategy=conflate::option::overwrite_none)]
+    max_view_size: Option<ByteSize>,
+
+    /// Number of trees of recently visited dirs kept in memory to avoid fetching them again [default: 100]
+    #[merge(strategy=conflate::option::overwrite_none)]
+    tree_cache_size: Option<usize>,
+
+    /// Format of the times shown in dir listings: a strftime format string, "relative" or "iso" [default: "%Y-%m-%d %H:%M:%S"]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[merge(strategy=conflate::option::overwrite_none)]
+    time_format: Option<TimeFormat>,
+
+    /// Don't color the entries of dir listings by their type
+    #[merge(strategy=conflate::bool::overwrite_false)]
+    no_colors: bool,
+
+    /// Don't wrap around when moving up from the first or down from the last row of a table
+    #[merge(strategy=conflate::bool::overwrite_false)]
+    no_wrap_around: bool,
+}
+
+impl TuiOptions {
+    /// The chunk size to use when reading file contents, clamped to sane bounds
+    pub fn read_chunk_size(&self) -> usize {
+        clamp_chunk_size(
+            self.read_chunk_size
+                .map_or(DEFAULT_CHUNK_SIZE, |size| size.0),
+        )
+    }
+
+    /// The maximum number of bytes to read when viewing files
+    pub fn max_view_size(&self) -> u64 {
+        self.max_view_size
+            .map_or(DEFAULT_MAX_VIEW_SIZE, |size| size.0)
+    }
+
+    /// The number of trees to keep in memory when browsing snapshots
+    pub fn tree_cache_size(&self) -> usize {
+        self.tree_cache_size.unwrap_or(DEFAULT_TREE_CACHE_SIZE)
+    }
+
+    /// The format of the times shown in dir listings
+    pub fn time_format(&self) -> TimeFormat {
+        self.time_format
+            .clone()
+            .unwrap_or_else(|| TimeFormat::Custom(DEFAULT_TIME_FORMAT.to_string()))
+    }
+
+    /// Whether to color the entries of dir listings
+    pub const fn colors(&self) -> bool {
+        !self.no_colors
+    }
+
+    /// Whether moving the selection in tables wraps around at the first and last row
+    pub const fn wrap_around(&self) -> bool {
+        !self.no_wrap_around
+    }
+}
+
+/// Work run in a background thread while the UI keeps processing events
+pub type Job<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+/// Apps which run jobs in the background
+pub trait Background<'a> {
+    /// Take the next job to start in the background, if any
+    fn take_job(&mut self) -> Option<Job<'a>>;
+
+    /// Process the results of the jobs running in the background
+    ///
+    /// Returns whether jobs are still running.
+    fn update(&mut self) -> bool;
+}
+
 pub trait TuiResult {
     fn exit(&self) -> bool;
 }
@@ -74,27 +186,128 @@ fn reset_terminal() -> Result<()> {
     Ok(())
 }
 
-pub fn run_app<T: TuiResult, A: Draw + ProcessEvent<Result = Result<T>>, B: Backend>(
+/// Whether the terminal needs to be cleared before drawing, e.g. after it was suspended
+static CLEAR_TERMINAL: AtomicBool = AtomicBool::new(false);
+
+/// Temporarily leaves the interactive UI to run `f`, e.g. to write to stdout
+pub fn suspend<T>(f: impl FnOnce() -> T) -> Result<T> {
+    reset_terminal()?;
+    let result = f();
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    enable_raw_mode()?;
+    CLEAR_TERMINAL.store(true, Ordering::Relaxed);
+    Ok(result)
+}
+
+/// How text was copied to the clipboard
+pub enum Copied {
+    /// to the clipboard of the system
+    System,
+    /// sent to the terminal using the OSC 52 escape sequence, which the terminal may silently ignore
+    Terminal,
+}
+
+thread_local! {
+    // the system clipboard, kept open as on some systems its contents are lost when it is closed
+    static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+}
+
+/// Copies text to the clipboard
+///
+/// The clipboard of the system is used if available. Else (e.g. over SSH) the text is sent to the
+/// terminal using the OSC 52 escape sequence; as terminals may ignore it, the text should be shown then.
+pub fn copy_to_clipboard(text: &str) -> Result<Copied> {
+    let copied = CLIPBOARD.with_borrow_mut(|clipboard| -> Result<(), arboard::Error> {
+        if clipboard.is_none() {
+            *clipboard = Some(arboard::Clipboard::new()?);
+        }
+        clipboard.as_mut().unwrap().set_text(text)
+    });
+    match copied {
+        Ok(()) => return Ok(Copied::System),
+        Err(err) => debug!("system clipboard is not available: {err}"),
+    }
+
+    // terminals known to not support OSC 52
+    if let Ok(term @ ("dumb" | "linux")) = std::env::var("TERM").as_deref() {
+        bail!("clipboard is not available in this terminal (TERM={term})");
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(Copied::Terminal)
+}
+
+/// Runs `f` while watching for Esc in the background, which sets the cancellation token passed to `f`
+///
+/// All other key presses are discarded while `f` is running.
+pub fn cancel_on_esc<T>(f: impl FnOnce(&AtomicBool) -> T) -> T {
+    let cancel = AtomicBool::new(false);
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        _ = s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                if !event::poll(Duration::from_millis(100)).unwrap_or_default() {
+                    continue;
+                }
+                if let Ok(Event::Key(key)) = event::read() {
+                    if key.kind == KeyEventKind::Press && key.code == KeyCode::Esc {
+                        cancel.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+        });
+        let result = f(&cancel);
+        done.store(true, Ordering::Relaxed);
+        result
+    })
+}
+
+pub fn run_app<
+    'a,
+    T: TuiResult,
+    A: Draw + ProcessEvent<Result = Result<T>> + Background<'a>,
+    B: Backend,
+>(
     terminal: Arc<RwLock<Terminal<B>>>,
-    mut app: A,
+    app: A,
 ) -> Result<()> {
-    loop {
-        _ = terminal.write().unwrap().draw(|f| ui(f, &mut app))?;
-        let event = event::read()?;
-
-        if let Event::Key(KeyEvent {
-            code: KeyCode::Char('c'),
-            modifiers: KeyModifiers::CONTROL,
-            kind: KeyEventKind::Press,
-            ..
-        }) = event
-        {
-            return Ok(());
-        }
-        if app.input(event)?.exit() {
-            return Ok(());
+    // the app is moved into the scope, so it is dropped (which cancels its jobs) before the
+    // background threads are joined
+    thread::scope(move |s| -> Result<()> {
+        let mut app = app;
+        loop {
+            while let Some(job) = app.take_job() {
+                _ = s.spawn(job);
+            }
+            let busy = app.update();
+            {
+                let mut terminal = terminal.write().unwrap();
+                if CLEAR_TERMINAL.swap(false, Ordering::Relaxed) {
+                    terminal.clear()?;
+                }
+                _ = terminal.draw(|f| ui(f, &mut app))?;
+            }
+            // while jobs are running, only wait for events until the next update
+            if busy && !event::poll(UPDATE_INTERVAL)? {
+                continue;
+            }
+            let event = event::read()?;
+
+            if let Event::Key(KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event
+            {
+                return Ok(());
+            }
+            if app.input(event)?.exit() {
+                return Ok(());
+            }
         }
-    }
+    })
 }
 
 fn ui<A: Draw>(f: &mut Frame<'_>, app: &mut A) {
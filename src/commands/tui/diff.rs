@@ -3,29 +3,32 @@ use std::{ffi::OsString, path::PathBuf};
 use anyhow::Result;
 use crossterm::event::{Event, KeyCode, KeyEventKind};
 use itertools::{EitherOrBoth, Itertools};
+use log::warn;
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Paragraph},
 };
 use rustic_core::{
-    IndexedFull, Progress, ProgressBars, Repository,
+    IndexedFull, ProgressBars, Repository,
     repofile::{Node, SnapshotFile, Tree},
 };
 use style::palette::tailwind;
 
 use crate::{
+    Application, RUSTIC_APP,
     commands::{
         diff::{DiffStatistics, NodeDiff},
         snapshots::fill_table,
         tui::{
-            summary::BlobInfoRef,
+            Background, Job,
+            summary::{BlobInfoRef, SummaryJob},
             widgets::{
                 Draw, PopUpPrompt, PopUpText, ProcessEvent, PromptResult, SelectTable, WithBlock,
                 popup_prompt, popup_text,
             },
         },
     },
-    helpers::bytes_size_to_string,
+    helpers::{DisplayTimeZone, bytes_size_to_string},
 };
 
 use super::{
@@ -38,6 +41,7 @@ use super::{
 enum CurrentScreen {
     Diff,
     ShowHelp(PopUpText),
+    ShowMessage(PopUpText),
     SnapshotDetails(PopUpTable),
     PromptExit(PopUpPrompt),
     PromptLeave(PopUpPrompt),
@@ -51,7 +55,7 @@ Diff Commands:
 
           m : toggle ignoring metadata
           d : toggle show only different entries
-          s : compute information for (sub-)dirs
+          s : compute information for (sub-)dirs (press Esc to cancel)
           I : show information about snapshots
 
 General Commands:
@@ -141,8 +145,11 @@ pub struct Diff<'a, P, S> {
     tree: DiffTree,
     node: DiffNode,
     summary_map: SummaryMap,
+    sizes: Option<SummaryJob>, // the computation of (sub)-dir information running in the background
+    job: Option<Job<'a>>,      // the job to start in the background
     ignore_metadata: bool,
     ignore_identical: bool,
+    time_zone: DisplayTimeZone,
 }
 
 pub enum DiffResult {
@@ -157,7 +164,7 @@ impl TuiResult for DiffResult {
     }
 }
 
-impl<'a, P: ProgressBars, S: IndexedFull> Diff<'a, P, S> {
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> Diff<'a, P, S> {
     pub fn new(
         repo: &'a Repository<P, S>,
         snap_left: SnapshotFile,
@@ -186,7 +193,10 @@ impl<'a, P: ProgressBars, S: IndexedFull> Diff<'a, P, S> {
         let mut tree = DiffTree::from_node(repo, &node)?;
         let mut app = Self {
             current_screen: CurrentScreen::Diff,
-            table: WithBlock::new(SelectTable::new(header), Block::new()),
+            table: WithBlock::new(
+                SelectTable::new(header, RUSTIC_APP.config().tui.wrap_around()),
+                Block::new(),
+            ),
             repo,
             snapshot_left: snap_left,
             snapshot_right: snap_right,
@@ -196,8 +206,11 @@ impl<'a, P: ProgressBars, S: IndexedFull> Diff<'a, P, S> {
             tree: DiffTree::default(),
             node,
             summary_map,
+            sizes: None,
+            job: None,
             ignore_metadata: true,
             ignore_identical: true,
+            time_zone: RUSTIC_APP.config().global.time_zone(),
         };
         tree.nodes.retain(|node| app.show_node(node));
         app.tree = tree;
@@ -244,7 +257,7 @@ impl<'a, P: ProgressBars, S: IndexedFull> Diff<'a, P, S> {
                 bytes_size_to_string(size),
                 node.meta.mtime.map_or_else(
                     || "?".to_string(),
-                    |t| format!("{}", t.format("%Y-%m-%d %H:%M:%S")),
+                    |t| self.time_zone.format(&t, "%Y-%m-%d %H:%M:%S"),
                 ),
             )
         };
@@ -381,34 +394,29 @@ impl<'a, P: ProgressBars, S: IndexedFull> Diff<'a, P, S> {
         Ok(())
     }
 
-    pub fn compute_summary(&mut self) -> Result<()> {
-        let pb = self.repo.progress_bars();
-        let p = pb.progress_counter("computing (sub)-dir information");
-
-        let (left, right) = self.node.0.as_ref().left_and_right();
-        if let Some(node) = left {
-            if let Some(id) = node.subtree {
-                self.summary_map.compute(self.repo, id, &p)?;
-            }
+    /// Start computing the summaries of the dirs on both sides in the background
+    pub fn compute_summary(&mut self) {
+        if self.sizes.is_some() {
+            return;
         }
-        if let Some(node) = right {
-            if let Some(id) = node.subtree {
-                self.summary_map.compute(self.repo, id, &p)?;
-            }
-        }
-
-        p.finish();
-        self.update_table();
-        Ok(())
+        let (left, right) = self.node.0.as_ref().left_and_right();
+        let ids: Vec<_> = left
+            .into_iter()
+            .chain(right)
+            .filter_map(|node| node.subtree)
+            .collect();
+        let (sizes, job) = SummaryJob::new(self.repo, ids, &self.summary_map);
+        self.sizes = Some(sizes);
+        self.job = Some(job);
     }
 
     pub fn snapshot_details(&self) -> PopUpTable {
         let mut rows = Vec::new();
         let mut rows_right = Vec::new();
-        fill_table(&self.snapshot_left, |title, value| {
+        fill_table(&self.snapshot_left, self.time_zone, |title, value| {
             rows.push(vec![Text::from(title.to_string()), Text::from(value)]);
         });
-        fill_table(&self.snapshot_right, |_, value| {
+        fill_table(&self.snapshot_right, self.time_zone, |_, value| {
             rows_right.push(Text::from(value));
         });
         for (row, right) in rows.iter_mut().zip(rows_right) {
@@ -418,7 +426,7 @@ impl<'a, P: ProgressBars, S: IndexedFull> Diff<'a, P, S> {
     }
 }
 
-impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Diff<'a, P, S> {
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> ProcessEvent for Diff<'a, P, S> {
     type Result = Result<DiffResult>;
     fn input(&mut self, event: Event) -> Result<DiffResult> {
         use KeyCode::{Backspace, Char, Enter, Esc, Left, Right};
@@ -436,6 +444,12 @@ impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Diff<'a, P, S> {
                             self.goback();
                         }
                     }
+                    // while computing sizes, Esc cancels the computation instead of exiting
+                    Esc if self.sizes.is_some() => {
+                        if let Some(sizes) = &self.sizes {
+                            sizes.cancel();
+                        }
+                    }
                     Esc | Char('q') => {
                         self.current_screen = CurrentScreen::PromptExit(popup_prompt(
                             "exit rustic",
@@ -448,7 +462,7 @@ impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Diff<'a, P, S> {
                     }
                     Char('m') => self.toggle_ignore_metadata(),
                     Char('d') => self.toggle_ignore_identical()?,
-                    Char('s') => self.compute_summary()?,
+                    Char('s') => self.compute_summary(),
                     Char('I') => {
                         self.current_screen =
                             CurrentScreen::SnapshotDetails(self.snapshot_details());
@@ -457,7 +471,9 @@ impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Diff<'a, P, S> {
                 },
                 _ => {}
             },
-            CurrentScreen::SnapshotDetails(_) | CurrentScreen::ShowHelp(_) => match event {
+            CurrentScreen::SnapshotDetails(_)
+            | CurrentScreen::ShowHelp(_)
+            | CurrentScreen::ShowMessage(_) => match event {
                 Event::Key(key) if key.kind == KeyEventKind::Press => {
                     if matches!(key.code, Char('q' | ' ' | 'I' | '?') | Esc | Enter) {
                         self.current_screen = CurrentScreen::Diff;
@@ -482,7 +498,36 @@ impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Diff<'a, P, S> {
     }
 }
 
-impl<'a, P: ProgressBars, S: IndexedFull> Draw for Diff<'a, P, S> {
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> Background<'a> for Diff<'a, P, S> {
+    fn take_job(&mut self) -> Option<Job<'a>> {
+        self.job.take()
+    }
+
+    fn update(&mut self) -> bool {
+        let Some(sizes) = &mut self.sizes else {
+            return false;
+        };
+        let (merged, finished) = sizes.update(&mut self.summary_map);
+        if merged {
+            self.update_table();
+        }
+        let Some(result) = finished else {
+            return true;
+        };
+        self.sizes = None;
+        if let Err(err) = result {
+            warn!("error computing (sub)-dir information: {err:?}");
+            if matches!(self.current_screen, CurrentScreen::Diff) {
+                let message = format!("error computing (sub)-dir information: {err}");
+                self.current_screen =
+                    CurrentScreen::ShowMessage(popup_text("compute sizes", message.into()));
+            }
+        }
+        false
+    }
+}
+
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> Draw for Diff<'a, P, S> {
     fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
         let rects = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).split(area);
 
@@ -492,7 +537,11 @@ impl<'a, P: ProgressBars, S: IndexedFull> Draw for Diff<'a, P, S> {
         // draw the footer
         let buffer_bg = tailwind::SLATE.c950;
         let row_fg = tailwind::SLATE.c200;
-        let info_footer = Paragraph::new(Line::from(INFO_TEXT))
+        let footer = self
+            .sizes
+            .as_ref()
+            .map_or_else(|| Line::from(INFO_TEXT), |sizes| Line::from(sizes.status()));
+        let info_footer = Paragraph::new(footer)
             .style(Style::new().fg(row_fg).bg(buffer_bg))
             .centered();
         f.render_widget(info_footer, rects[1]);
@@ -501,7 +550,9 @@ impl<'a, P: ProgressBars, S: IndexedFull> Draw for Diff<'a, P, S> {
         match &mut self.current_screen {
             CurrentScreen::Diff => {}
             CurrentScreen::SnapshotDetails(popup) => popup.draw(area, f),
-            CurrentScreen::ShowHelp(popup) => popup.draw(area, f),
+            CurrentScreen::ShowHelp(popup) | CurrentScreen::ShowMessage(popup) => {
+                popup.draw(area, f)
+            }
             CurrentScreen::PromptExit(popup) | CurrentScreen::PromptLeave(popup) => {
                 popup.draw(area, f);
             }
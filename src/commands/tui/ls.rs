@@ -1,73 +1,523 @@
-use std::path::{Path, PathBuf};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    iter::once,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    sync::atomic,
+};
 
-use anyhow::Result;
-use crossterm::event::{Event, KeyCode, KeyEventKind};
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local};
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use directories::ProjectDirs;
+use log::warn;
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Paragraph},
 };
 use rustic_core::{
-    IndexedFull, Progress, ProgressBars, Repository, TreeId,
-    repofile::{Node, SnapshotFile, Tree},
+    Id, IndexedFull, LsOptions, Progress, ProgressBars, Repository, TreeId,
+    repofile::{Metadata, Node, NodeType, SnapshotFile, Tree},
 };
+use serde::{Deserialize, Serialize};
 use style::palette::tailwind;
 
 use crate::{
+    Application, RUSTIC_APP,
     commands::{
-        ls::{NodeLs, Summary},
+        diff::NodeDiff,
+        ls::{NodeLs, Summary, cmp_paths, device_numbers},
         tui::{
+            Copied, cancel_on_esc, copy_to_clipboard,
+            diff::{Diff, DiffResult},
             restore::Restore,
+            suspend,
             widgets::{
-                Draw, PopUpPrompt, PopUpText, ProcessEvent, PromptResult, SelectTable,
-                TextInputResult, WithBlock, popup_prompt, popup_scrollable_text, popup_text,
+                Draw, PopUpBarChart, PopUpPrompt, PopUpText, ProcessEvent, PromptResult,
+                SelectTable, TextInputResult, WithBlock, popup_bar_chart, popup_input,
+                popup_prompt, popup_scrollable_text, popup_text,
             },
         },
     },
-    helpers::bytes_size_to_string,
+    helpers::{DisplayTimeZone, TimeFormat, bytes_size_to_string},
+    mime::MimeCache,
+};
+
+use super::{
+    Background, Job,
+    summary::{BlobInfoRef, StoredSizeCache, SummaryJob, SummaryMap},
+    widgets::PopUpInput,
 };
 
-use super::{summary::SummaryMap, widgets::PopUpInput};
+// what to do with the contents of the selected file
+#[derive(Clone, Copy)]
+enum ContentAction {
+    View,
+    Print,
+    Open,
+}
 
 // the states this screen can be in
 enum CurrentScreen<'a, P, S> {
     Snapshot,
     ShowHelp(PopUpText),
     Restore(Box<Restore<'a, P, S>>),
+    GetDiffSnapshot(PopUpInput),
+    Diff(Box<Diff<'a, P, S>>),
     PromptExit(PopUpPrompt),
-    ShowFile(Box<PopUpInput>),
+    PromptViewCold(PopUpPrompt, ContentAction),
+    ShowFile(Box<FileView>),
+    SaveFile(PopUpInput),
+    Export(PopUpInput),
+    RestoreScript(PopUpInput),
+    GotoPath(PopUpInput),
+    GotoTree(PopUpInput),
+    Search(PopUpInput),
+    Filter(PopUpInput),
+    SearchRecursive(PopUpInput),
+    ShowNodeInfo(PopUpInput),
+    ShowBlobs(PopUpInput),
+    ShowSharedPaths(PopUpInput),
+    ShowSizeChart(PopUpBarChart),
+    PickColumns(PopUpText),
+    ShowMessage(PopUpText),
 }
 
 const INFO_TEXT: &str = "(Esc) quit | (Enter) enter dir | (Backspace) return to parent | (v) view | (r) restore | (?) show all commands";
 
+/// Maximum number of entries shown individually in the size chart
+const MAX_CHART_BARS: usize = 20;
+
+/// Maximum length of the names shown in the size chart
+const MAX_CHART_NAME_LEN: usize = 30;
+
 const HELP_TEXT: &str = r"
 Ls Commands:
 
-          v : view file contents (text files only, up to 1MiB)
-          r : restore selected item
-          n : toggle numeric IDs
-          s : compute information for (sub)-dirs
-          D : diff current selection
+          I : show all metadata, permissions and extended attributes of
+              selected item
+          B : show the content blob ids (subtree id for dirs) of selected item
+              and the pack files containing them
+          = : show all paths of dirs with the same contents (tree) as the
+              selected dir (press Esc to cancel); after computing information
+              with s, such dirs are marked with = in the first column
+          U : show a chart of the sizes of the entries in the current dir
+              (sizes of dirs must be computed with s first)
+          C : toggle split view comparing with the current dir
+          M : toggle column with content type detected by magic bytes
+          K : choose the shown columns (press 1-6 to toggle a column)
+          v : view file contents (non-text files as hex dump, size limited by
+              the max-view-size option; on hot/cold repositories you are asked
+              first, as the contents are read from the cold storage)
+              (in the viewer press w to save the file locally, h to toggle
+               between text and hex dump, f to toggle pretty-printing JSON
+               and TOML, l to toggle line numbers, / to search, n,N to jump
+               to next/previous match, : to go to a line and y to copy the
+               content to the clipboard)
+          V : open selected file with $PAGER or $EDITOR (built-in viewer if
+              none is set)
+          p : write full contents of selected file to stdout
+              (on hot/cold repositories, V and p ask first like v)
+      Space : toggle mark for selected item
+          r : restore marked items (selected item if none is marked)
+     Ctrl-r : restore marked items (selected item if none is marked) to their
+              original location after confirmation (only for snapshots with
+              absolute paths)
+          R : write a shell script with restore commands for marked items
+              (selected item if none is marked) instead of restoring
+          y : copy snapshot path of selected item to clipboard
+          e : export listing of current dir to a CSV file (JSON if the
+              file name ends with .json)
+          u : toggle numeric IDs
+          t : cycle time column (modification, access, change time)
+          / : search for name
+          f : filter current dir by name (Esc in the prompt clears the filter)
+     Ctrl-f : search names in all dirs below the current dir and show the
+              matches in the flat view (press Esc to cancel)
+          H : toggle hiding hidden files (names starting with a dot)
+          A : toggle showing the full path instead of the name
+          P : toggle showing only entries changed since the parent snapshot
+              (marked with + for new, M for changed contents, U for changed
+              metadata and T for changed type)
+        n,N : jump to next/previous match of the last search
+ other keys : jump to next item starting with the pressed letter or digit
+          s : compute information for (sub)-dirs (press Esc to cancel)
+          S : toggle showing sizes as stored in the repository (compressed and
+              deduplicated, for dirs only after computing information)
+          D : diff current dir with another snapshot
+          F : toggle flat view of all files below current dir
+          T : show the tree with the given id (e.g. to inspect trees not
+              reachable from the snapshot)
+          g : go to path or glob (relative to current dir or absolute)
+          c : toggle fast descend through single-child dirs
+          L : toggle following symlinks to dirs within the snapshot on enter
+          i : toggle case-insensitive sorting by name
+          o : cycle sort column (name, size, mode, user, group, time)
+          O : reverse sort direction
+          d : toggle sorting dirs before files
 
 General Commands:
 
       q,Esc : exit
       Enter : enter dir
   Backspace : return to parent dir
+          ^ : return to snapshot root
+        Tab : switch to next snapshot (if several are shown in tabs)
+  Shift-Tab : switch to previous snapshot
           ? : show this help page
 
+The sorting, hidden files, numeric IDs, time column and shown columns settings
+are kept for the next session when exiting.
  ";
 
 pub(crate) struct Snapshot<'a, P, S> {
     current_screen: CurrentScreen<'a, P, S>,
     numeric: bool,
+    flat: Option<String>, // description of the flat view, if shown
+    fast_descend: bool,
+    follow_symlinks: bool,
+    ignore_case: bool,
+    sort_key: SortKey,
+    sort_reverse: bool,
+    dirs_first: bool,
+    order: Vec<usize>, // indices into `tree.nodes` in the order shown
     table: WithBlock<SelectTable>,
+    frozen: Option<Frozen>,
+    show_mime: bool,
+    show_stored: bool, // show the size of the contents as stored in the repository
+    colors: bool,      // color rows by node type
+    wrap_around: bool, // whether Up/Down wrap around in the tables
+    cold_read_confirmed: bool, // reading file contents from the cold storage was confirmed
+    time_format: TimeFormat,
+    time_zone: DisplayTimeZone,
+    time_field: TimeField,
+    hidden_columns: BTreeSet<Column>,
+    mime_cache: MimeCache,
+    stored_sizes: StoredSizeCache,
+    last_search: Option<String>,
+    marked: BTreeSet<usize>,      // indices into `tree.nodes` of marked nodes
+    hide_hidden: bool,            // don't show nodes whose name starts with a dot
+    full_path: bool,              // show the full path instead of the name in the name column
+    since_parent: Option<TreeId>, // root tree of the parent snapshot if only changed entries are shown
+    changes: Option<BTreeMap<OsString, NodeDiff>>, // changes of the current dir compared to the parent
+    shared_trees: BTreeSet<TreeId>, // trees occurring more than once in the snapshot, as far as known
+    filter: Option<String>, // only show nodes of the current dir whose name contains this (ignoring case)
     repo: &'a Repository<P, S>,
     snapshot: SnapshotFile,
     path: PathBuf,
-    trees: Vec<(Tree, TreeId, usize)>, // Stack of parent trees with position
+    trees: Vec<(Tree, TreeId, usize, PathBuf)>, // Stack of parent trees with position and path
     tree: Tree,
     tree_id: TreeId,
+    tree_cache: TreeCache,
+    positions: BTreeMap<TreeId, usize>, // last selected index into the nodes of each visited dir
     summary_map: SummaryMap,
+    sizes: Option<SummaryJob>, // the computation of (sub)-dir sizes running in the background
+    job: Option<Job<'a>>,      // the job to start in the background
+}
+
+/// The trees of recently visited dirs, least recently used first
+struct TreeCache {
+    capacity: usize,
+    trees: VecDeque<(TreeId, Tree)>,
+}
+
+impl TreeCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            trees: VecDeque::new(),
+        }
+    }
+
+    /// Get a tree from the cache or, if not cached, from the repository
+    ///
+    /// While fetching from the repository, which may take a while for big dirs or slow backends,
+    /// a spinner is shown.
+    fn get<P: ProgressBars, S: IndexedFull>(
+        &mut self,
+        repo: &Repository<P, S>,
+        id: TreeId,
+    ) -> Result<Tree> {
+        if let Some(pos) = self.trees.iter().position(|(tree_id, _)| *tree_id == id) {
+            let entry = self.trees.remove(pos).unwrap();
+            let tree = entry.1.clone();
+            self.trees.push_back(entry);
+            return Ok(tree);
+        }
+        let p = repo.progress_bars().progress_spinner("loading dir...");
+        let tree = repo.get_tree(&id);
+        p.finish();
+        let tree = tree?;
+        if self.capacity > 0 {
+            if self.trees.len() >= self.capacity {
+                _ = self.trees.pop_front();
+            }
+            self.trees.push_back((id, tree.clone()));
+        }
+        Ok(tree)
+    }
+}
+
+/// The column the ls table is sorted by
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Mode,
+    User,
+    Group,
+    Time,
+}
+
+impl SortKey {
+    const fn next(self) -> Self {
+        match self {
+            Self::Name => Self::Size,
+            Self::Size => Self::Mode,
+            Self::Mode => Self::User,
+            Self::User => Self::Group,
+            Self::Group => Self::Time,
+            Self::Time => Self::Name,
+        }
+    }
+
+    const fn title(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Size => "size",
+            Self::Mode => "mode",
+            Self::User => "user",
+            Self::Group => "group",
+            Self::Time => "time",
+        }
+    }
+}
+
+/// The timestamp shown in the time column
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TimeField {
+    #[default]
+    Modified,
+    Accessed,
+    Changed,
+}
+
+impl TimeField {
+    const fn next(self) -> Self {
+        match self {
+            Self::Modified => Self::Accessed,
+            Self::Accessed => Self::Changed,
+            Self::Changed => Self::Modified,
+        }
+    }
+
+    const fn title(self) -> &'static str {
+        match self {
+            Self::Modified => "Time (mtime)",
+            Self::Accessed => "Time (atime)",
+            Self::Changed => "Time (ctime)",
+        }
+    }
+
+    const fn get(self, meta: &Metadata) -> Option<DateTime<Local>> {
+        match self {
+            Self::Modified => meta.mtime,
+            Self::Accessed => meta.atime,
+            Self::Changed => meta.ctime,
+        }
+    }
+}
+
+/// A column of the ls table which can be hidden
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Column {
+    Name,
+    Size,
+    Mode,
+    User,
+    Group,
+    Time,
+}
+
+impl Column {
+    /// All columns in the order they are shown, i.e. the order of [`Snapshot::ls_fields`]
+    const ALL: [Self; 6] = [
+        Self::Name,
+        Self::Size,
+        Self::Mode,
+        Self::User,
+        Self::Group,
+        Self::Time,
+    ];
+}
+
+/// Version of the stored view preferences
+const VIEW_PREFS_VERSION: u32 = 1;
+
+/// View preferences of the snapshot browser which are kept between sessions
+///
+/// Missing fields get their default value, so fields can be added in later versions.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct ViewPrefs {
+    version: u32,
+    numeric: bool,
+    sort_key: SortKey,
+    sort_reverse: bool,
+    dirs_first: bool,
+    ignore_case: bool,
+    hide_hidden: bool,
+    time_field: TimeField,
+    hidden_columns: BTreeSet<Column>,
+}
+
+impl ViewPrefs {
+    /// The file storing the view preferences in the data dir of the user
+    fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "rustic").map(|dirs| dirs.data_dir().join("tui-view.json"))
+    }
+
+    /// Load the stored view preferences; if there are none, they are corrupt or were stored by
+    /// another version, the defaults are used
+    fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .filter(|prefs: &Self| prefs.version == VIEW_PREFS_VERSION)
+            .unwrap_or_default()
+    }
+
+    /// Store the view preferences, overwriting existing (possibly corrupt) ones
+    fn save(&self) -> Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The start of a file shown in the viewer
+struct FileView {
+    popup: PopUpInput,
+    data: Vec<u8>,
+    hex: bool,
+    pretty: bool, // whether the content is shown reformatted
+    title: String,
+    shown_title: String,
+    query: String,
+    matches: usize,
+    search: Option<PopUpInput>,
+    goto_line: Option<PopUpInput>,
+    copy_choice: Option<PopUpText>,
+    message: Option<PopUpText>,
+}
+
+impl FileView {
+    /// Search for `query`, jumping to the first match and showing the number of matches in the title
+    fn search_for(&mut self, query: &str) {
+        self.matches = self.popup.0.widget.search(query);
+        self.query = query.to_string();
+    }
+
+    /// Update the title with the first shown line and the search results
+    ///
+    /// Returns whether the title changed.
+    fn update_title(&mut self) -> bool {
+        let (top, lines) = self.popup.0.widget.top_line();
+        let mut title = format!("{} - line {}/{lines}", self.title, top + 1);
+        if !self.query.is_empty() {
+            title = format!("{title} - {} matches for \"{}\"", self.matches, self.query);
+        }
+        let changed = title != self.shown_title;
+        if changed {
+            self.popup.0.block = Block::bordered().title(title.clone());
+            self.shown_title = title;
+        }
+        changed
+    }
+
+    fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
+        self.popup.draw(area, f);
+        // the shown lines are only known after drawing
+        if self.update_title() {
+            self.popup.draw(area, f);
+        }
+        for popup in [&mut self.search, &mut self.goto_line]
+            .into_iter()
+            .flatten()
+        {
+            popup.draw(area, f);
+        }
+        for popup in [&mut self.copy_choice, &mut self.message]
+            .into_iter()
+            .flatten()
+        {
+            popup.draw(area, f);
+        }
+    }
+
+    /// Copy the shown content (`all`) or only the visible page to the clipboard and show the result
+    fn copy(&mut self, all: bool) {
+        let widget = &self.popup.0.widget;
+        let (text, what) = if all {
+            (widget.text(), "content")
+        } else {
+            (widget.visible_text(), "visible page")
+        };
+        let amount = format!(
+            "{} lines, {}",
+            text.lines().count(),
+            bytes_size_to_string(text.len() as u64)
+        );
+        let msg = match copy_to_clipboard(&text) {
+            Ok(Copied::System) => format!("copied {what} ({amount}) to clipboard"),
+            Ok(Copied::Terminal) => format!(
+                "sent {what} ({amount}) to the terminal to copy it to the clipboard; \
+                 nothing is copied if the terminal doesn't support this (OSC 52)"
+            ),
+            Err(err) => format!("cannot copy to clipboard: {err}"),
+        };
+        self.message = Some(popup_text("copy content", msg.into()));
+    }
+}
+
+/// The color of a row showing `node`: blue for dirs, cyan for symlinks and green for executable files
+fn node_color(node: &Node) -> Option<Color> {
+    match node.node_type {
+        NodeType::Dir => Some(tailwind::BLUE.c400),
+        NodeType::Symlink { .. } => Some(tailwind::CYAN.c400),
+        NodeType::File if node.meta.mode.is_some_and(|mode| (mode & 0o111) != 0) => {
+            Some(tailwind::GREEN.c400)
+        }
+        _ => None,
+    }
+}
+
+/// A node of the flat view, which is named by its `path` relative to the current dir
+fn flat_node(path: &Path, node: Node) -> Node {
+    let mut flat_node = Node::new_node(path.as_os_str(), node.node_type, node.meta);
+    flat_node.content = node.content;
+    flat_node.subtree = node.subtree;
+    flat_node
+}
+
+/// A frozen dir listing which is shown in the left pane of the split view
+struct Frozen {
+    tree: Tree,
+    path: PathBuf,
+    table: WithBlock<SelectTable>,
 }
 
 pub enum SnapshotResult {
@@ -76,36 +526,113 @@ pub enum SnapshotResult {
     None,
 }
 
-impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> Snapshot<'a, P, S> {
     pub fn new(
         repo: &'a Repository<P, S>,
         snapshot: SnapshotFile,
         summary_map: SummaryMap,
     ) -> Result<Self> {
-        let header = ["Name", "Size", "Mode", "User", "Group", "Time"]
-            .into_iter()
-            .map(Text::from)
-            .collect();
-
         let tree_id = snapshot.tree;
-        let tree = repo.get_tree(&tree_id)?;
+        let mut tree_cache = TreeCache::new(RUSTIC_APP.config().tui.tree_cache_size());
+        let tree = tree_cache.get(repo, tree_id)?;
+        let prefs = ViewPrefs::load();
         let mut app = Self {
             current_screen: CurrentScreen::Snapshot,
-            numeric: false,
-            table: WithBlock::new(SelectTable::new(header), Block::new()),
+            numeric: prefs.numeric,
+            flat: None,
+            fast_descend: false,
+            follow_symlinks: false,
+            ignore_case: prefs.ignore_case,
+            sort_key: prefs.sort_key,
+            sort_reverse: prefs.sort_reverse,
+            dirs_first: prefs.dirs_first,
+            order: Vec::new(),
+            table: WithBlock::new(
+                SelectTable::new(
+                    header(false, prefs.time_field, &prefs.hidden_columns),
+                    RUSTIC_APP.config().tui.wrap_around(),
+                ),
+                Block::new(),
+            ),
+            frozen: None,
+            show_mime: false,
+            show_stored: false,
+            colors: RUSTIC_APP.config().tui.colors(),
+            wrap_around: RUSTIC_APP.config().tui.wrap_around(),
+            cold_read_confirmed: false,
+            time_format: RUSTIC_APP.config().tui.time_format(),
+            time_zone: RUSTIC_APP.config().global.time_zone(),
+            time_field: prefs.time_field,
+            hidden_columns: prefs.hidden_columns,
+            mime_cache: MimeCache::default(),
+            stored_sizes: StoredSizeCache::default(),
+            last_search: None,
+            marked: BTreeSet::new(),
+            hide_hidden: prefs.hide_hidden,
+            full_path: false,
+            since_parent: None,
+            shared_trees: BTreeSet::new(),
+            changes: None,
+            filter: None,
             repo,
             snapshot,
             path: PathBuf::new(),
             trees: Vec::new(),
             tree,
             tree_id,
+            tree_cache,
+            positions: BTreeMap::new(),
             summary_map,
+            sizes: None,
+            job: None,
         };
+        app.update_shared_trees();
+        app.sort_tree();
         app.update_table();
         Ok(app)
     }
 
-    fn ls_row(&self, node: &Node) -> Vec<Text<'static>> {
+    fn ls_row(&self, node: &Node, dir: &Path) -> Vec<Text<'static>> {
+        let style = self
+            .colors
+            .then(|| node_color(node))
+            .flatten()
+            .map_or_else(Style::new, |color| Style::new().fg(color));
+        let mut fields = self.ls_fields(node, dir).into_iter();
+        // the name is the first field; emphasize where it matches the filter
+        let name = fields.next().unwrap_or_default();
+        let name = match &self.filter {
+            Some(filter) => highlight_matches(&name, &filter.to_lowercase(), self.match_style()),
+            None => Line::from(name),
+        };
+        self.visible_columns(
+            once(Text::from(name).style(style))
+                .chain(fields.map(|field| Text::from(field).style(style))),
+        )
+    }
+
+    /// Leave out the hidden columns from `fields`, which are in the order of [`Snapshot::ls_fields`]
+    fn visible_columns<T>(&self, fields: impl IntoIterator<Item = T>) -> Vec<T> {
+        visible_columns(fields, &self.hidden_columns)
+    }
+
+    /// The header of the tables with the currently shown columns
+    fn table_header(&self) -> Vec<Text<'static>> {
+        header(self.show_mime, self.time_field, &self.hidden_columns)
+    }
+
+    /// The style of the parts of names matching the filter
+    fn match_style(&self) -> Style {
+        let style = Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        if self.colors {
+            style.fg(tailwind::YELLOW.c300)
+        } else {
+            style
+        }
+    }
+
+    /// The fields shown for a node of the dir `dir` in the table
+    fn ls_fields(&self, node: &Node, dir: &Path) -> Vec<String> {
         let (user, group) = if self.numeric {
             (
                 node.meta
@@ -121,20 +648,62 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
                 node.meta.group.clone().unwrap_or_else(|| "?".to_string()),
             )
         };
-        let name = node.name().to_string_lossy().to_string();
-        let size = bytes_size_to_string(node.meta.size);
-        let mtime = node.meta.mtime.map_or_else(
+        let mut name = if self.full_path {
+            format!("/{}", dir.join(node.name()).display())
+        } else {
+            node.name().to_string_lossy().to_string()
+        };
+        if node.is_symlink() {
+            let target = node.node_type.to_link().to_string_lossy();
+            let target = if target.is_empty() { "?" } else { &target };
+            name = format!("{name} -> {target}");
+        }
+        // like `ls -l`, show the device numbers instead of a size for device nodes
+        let size = match node.node_type {
+            NodeType::Dev { device } | NodeType::Chardev { device } => {
+                let (major, minor) = device_numbers(device);
+                format!("{major}, {minor}")
+            }
+            _ => bytes_size_to_string(node.meta.size),
+        };
+        let time = self.time_field.get(&node.meta).map_or_else(
             || "?".to_string(),
-            |t| format!("{}", t.format("%Y-%m-%d %H:%M:%S")),
+            |t| self.time_format.format(&t, self.time_zone),
         );
-        [name, size, node.mode_str(), user, group, mtime]
+        let mime = self
+            .show_mime
+            .then(|| self.mime_cache.cached(node).unwrap_or("?").to_string());
+        [name, size, node.mode_str(), user, group, time]
             .into_iter()
-            .map(Text::from)
+            .chain(mime)
+            .collect()
+    }
+
+    /// Highlight a row which is only present on one side of the split view
+    fn highlight(row: Vec<Text<'static>>) -> Vec<Text<'static>> {
+        row.into_iter()
+            .map(|text| text.style(Style::new().fg(tailwind::AMBER.c400)))
             .collect()
     }
 
+    /// The index into `tree.nodes` of the selected row
+    fn selected_idx(&self) -> Option<usize> {
+        self.table
+            .widget
+            .selected()
+            .and_then(|row| self.order.get(row).copied())
+    }
+
+    /// The row showing the node with index `idx` into `tree.nodes`
+    fn row_of(&self, idx: usize) -> usize {
+        self.order
+            .iter()
+            .position(|&i| i == idx)
+            .unwrap_or_default()
+    }
+
     pub fn selected_node(&self) -> Option<&Node> {
-        self.table.widget.selected().map(|i| &self.tree.nodes[i])
+        self.selected_idx().map(|i| &self.tree.nodes[i])
     }
 
     pub fn update_table(&mut self) {
@@ -143,10 +712,22 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
         } else {
             Some(self.table.widget.selected().unwrap_or_default())
         };
-        let mut rows = Vec::new();
+        self.cache_stored_sizes();
+        // detecting content types is not supported on cold repositories
+        if self.show_mime && self.repo.config().is_hot != Some(true) {
+            let frozen_nodes = self.frozen.iter().flat_map(|frozen| &frozen.tree.nodes);
+            for node in self.tree.nodes.iter().chain(frozen_nodes) {
+                // errors are shown as unknown content type
+                _ = self.mime_cache.get(self.repo, node);
+            }
+        }
+
+        let frozen_names = self.frozen.as_ref().map(|frozen| names(&frozen.tree));
+        let mut nodes = Vec::new();
         let mut summary = Summary::default();
-        for node in &self.tree.nodes {
-            let mut node = node.clone();
+        for &idx in &self.order {
+            let mut node = self.tree.nodes[idx].clone();
+            let mut size_known = true;
             if node.is_dir() {
                 let id = node.subtree.unwrap();
                 if let Some(sum) = self.summary_map.get(&id) {
@@ -154,22 +735,131 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
                     node.meta.size = sum.summary.size;
                 } else {
                     summary.update(&node);
+                    size_known = false;
                 }
             } else {
                 summary.update(&node);
             }
-            let row = self.ls_row(&node);
-            rows.push(row);
+            if self.show_stored {
+                node.meta.size = self.stored_size(&node);
+            }
+            nodes.push((idx, node, size_known));
         }
+        let total_size = if self.show_stored {
+            nodes.iter().map(|(_, node, _)| node.meta.size).sum()
+        } else {
+            summary.size
+        };
+
+        // show the share of the total size once the sizes are computed; it follows the size column
+        let with_share = self.summary_map.get(&self.tree_id).is_some();
+        let share_column = 1 + [Column::Name, Column::Size]
+            .iter()
+            .filter(|column| !self.hidden_columns.contains(column))
+            .count();
+        let rows = nodes
+            .into_iter()
+            .map(|(idx, node, size_known)| {
+                let mark = if self.marked.contains(&idx) {
+                    "*".to_string()
+                } else if let Some(diff) = self
+                    .changes
+                    .as_ref()
+                    .and_then(|changes| changes.get(&node.name()))
+                {
+                    diff.to_string()
+                } else if node
+                    .subtree
+                    .is_some_and(|id| self.shared_trees.contains(&id))
+                {
+                    "=".to_string()
+                } else {
+                    " ".to_string()
+                };
+                let mut row = self.ls_row(&node, &self.path);
+                row.insert(0, Text::from(mark));
+                if with_share {
+                    let share = if size_known && total_size > 0 {
+                        format!("{:>3}%", node.meta.size * 100 / total_size)
+                    } else {
+                        String::new()
+                    };
+                    row.insert(share_column, Text::from(share));
+                }
+                if frozen_names
+                    .as_ref()
+                    .is_some_and(|names| !names.contains(&node.name()))
+                {
+                    row = Self::highlight(row);
+                }
+                row
+            })
+            .collect();
 
+        let mut table_header = self.table_header();
+        if with_share {
+            table_header.insert(share_column, Text::from("Share"));
+        }
+        self.table.widget.set_header(table_header);
         self.table.widget.set_content(rows, 1);
 
+        // the size of the whole snapshot is only known once the sizes are computed
+        // (the repository size is not shown, as it is not known without scanning all packs)
+        let snapshot_total =
+            self.summary_map
+                .get(&self.snapshot.tree)
+                .map_or_else(String::new, |sum| {
+                    format!(
+                        " - snapshot total: {}",
+                        bytes_size_to_string(sum.summary.size)
+                    )
+                });
         self.table.block = Block::new()
             .borders(Borders::BOTTOM | Borders::TOP)
-            .title(format!("{}:{}", self.snapshot.id, self.path.display()))
+            .title(format!(
+                "{}:{}{}{}{}{}{}{}{}",
+                self.snapshot.id,
+                self.path.display(),
+                self.flat
+                    .as_ref()
+                    .map_or_else(String::new, |flat| format!(" ({flat})")),
+                if self.fast_descend {
+                    " (fast descend)"
+                } else {
+                    ""
+                },
+                if self.follow_symlinks {
+                    " (following symlinks)"
+                } else {
+                    ""
+                },
+                if self.show_stored {
+                    " (stored sizes)"
+                } else {
+                    ""
+                },
+                if self.hide_hidden {
+                    " (hidden files filtered)"
+                } else {
+                    ""
+                },
+                if self.changes.is_some() {
+                    " (changed since parent)"
+                } else {
+                    ""
+                },
+                self.filter
+                    .as_ref()
+                    .map_or_else(String::new, |filter| format!(" [filter: {filter}]"))
+            ))
             .title_bottom(format!(
-                "total: {}, files: {}, dirs: {}, size: {} - {}",
-                self.tree.nodes.len(),
+                "total: {}, {}files: {}, dirs: {}, size: {} - {} - sorted by {}{}{}{}",
+                self.order.len(),
+                if self.marked.is_empty() {
+                    String::new()
+                } else {
+                    format!("marked: {}, ", self.marked.len())
+                },
                 summary.files,
                 summary.dirs,
                 summary.size,
@@ -177,35 +867,234 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
                     "numeric IDs"
                 } else {
                     " Id names"
-                }
+                },
+                self.sort_key.title(),
+                if self.sort_reverse { " (reversed)" } else { "" },
+                if self.dirs_first { ", dirs first" } else { "" },
+                snapshot_total
             ))
             .title_alignment(Alignment::Center);
         self.table.widget.select(old_selection);
+        self.update_frozen_table();
+    }
+
+    fn update_frozen_table(&mut self) {
+        let Some(frozen) = &self.frozen else {
+            return;
+        };
+        let current_names = names(&self.tree);
+        let rows = self
+            .sort_order(&frozen.tree.nodes)
+            .into_iter()
+            .map(|idx| {
+                let node = &frozen.tree.nodes[idx];
+                let mut row = self.ls_row(node, &frozen.path);
+                row.insert(0, Text::from(" "));
+                if current_names.contains(&node.name()) {
+                    row
+                } else {
+                    Self::highlight(row)
+                }
+            })
+            .collect();
+        if let Some(frozen) = &mut self.frozen {
+            frozen.table.widget.set_content(rows, 1);
+        }
+    }
+
+    pub fn toggle_mime(&mut self) {
+        self.show_mime = !self.show_mime;
+        self.reset_header();
+    }
+
+    /// Show or hide `column`
+    fn toggle_column(&mut self, column: Column) {
+        if !self.hidden_columns.remove(&column) {
+            _ = self.hidden_columns.insert(column);
+        }
+        self.reset_header();
+    }
+
+    /// The popup to choose the shown columns
+    fn column_picker(&self) -> PopUpText {
+        let lines: Vec<_> = Column::ALL
+            .into_iter()
+            .zip(header_names(false, self.time_field))
+            .enumerate()
+            .map(|(i, (column, name))| {
+                let shown = if self.hidden_columns.contains(&column) {
+                    ' '
+                } else {
+                    'x'
+                };
+                Line::from(format!("{} [{shown}] {name}", i + 1))
+            })
+            .chain([
+                Line::default(),
+                Line::from("press 1-6 to toggle a column, Esc to close"),
+            ])
+            .collect();
+        popup_text("columns", lines.into())
+    }
+
+    pub fn cycle_time_field(&mut self) {
+        self.time_field = self.time_field.next();
+        self.reset_header();
+        self.resort();
+    }
+
+    /// Recreate the tables with the current header
+    fn reset_header(&mut self) {
+        let selected = self.table.widget.selected();
+        self.table.widget = SelectTable::new(self.table_header(), self.wrap_around);
+        self.table.widget.select(selected);
+        let frozen_header = self.table_header();
+        if let Some(frozen) = &mut self.frozen {
+            frozen.table.widget = SelectTable::new(frozen_header, self.wrap_around);
+        }
+        self.update_table();
+    }
+
+    pub fn toggle_split(&mut self) {
+        if self.frozen.take().is_none() {
+            let block = Block::new()
+                .borders(Borders::BOTTOM | Borders::TOP)
+                .title(format!(
+                    "{}:{} (frozen)",
+                    self.snapshot.id,
+                    self.path.display()
+                ))
+                .title_alignment(Alignment::Center);
+            self.frozen = Some(Frozen {
+                tree: self.tree.clone(),
+                path: self.path.clone(),
+                table: WithBlock::new(
+                    SelectTable::new(self.table_header(), self.wrap_around),
+                    block,
+                ),
+            });
+        }
+        self.update_table();
     }
 
     pub fn enter(&mut self) -> Result<()> {
-        if let Some(idx) = self.table.widget.selected() {
+        let follow_symlinks = self.follow_symlinks;
+        let follow = move |node: &&Node| follow_symlinks && node.is_symlink();
+        // dirs in the flat view are entered by their path from the dir the view was created in
+        if self.flat.is_some() {
+            if let Some(node) = self.selected_node().filter(|node| node.is_dir()) {
+                let path = PathBuf::from(node.name());
+                _ = self.goback();
+                return self.goto(&path);
+            }
+            if let Some(node) = self.selected_node().filter(follow).cloned() {
+                let name = PathBuf::from(node.name());
+                let link_dir = self
+                    .path
+                    .join(name.parent().unwrap_or_else(|| Path::new("")));
+                _ = self.goback();
+                let idx = self.selected_idx().unwrap_or_default();
+                return self.enter_symlink(&node, link_dir, idx);
+            }
+        }
+        if let Some(idx) = self.selected_idx() {
+            self.remember_position();
             let node = &self.tree.nodes[idx];
+            if follow(&node) {
+                let (node, link_dir) = (node.clone(), self.path.clone());
+                return self.enter_symlink(&node, link_dir, idx);
+            }
             if node.is_dir() {
-                self.path.push(node.name());
-                let tree = self.tree.clone();
-                let tree_id = self.tree_id;
-                self.tree_id = node.subtree.unwrap();
-                self.tree = self.repo.get_tree(&self.tree_id)?;
-                self.trees.push((tree, tree_id, idx));
+                // read all trees before changing the state, so that the current dir is kept
+                // if a tree cannot be read
+                let mut path = self.path.join(node.name());
+                let mut tree_id = node.subtree.unwrap();
+                let mut tree = match self.tree_cache.get(self.repo, tree_id) {
+                    Ok(tree) => tree,
+                    Err(err) => {
+                        self.show_tree_error(&path, &err);
+                        return Ok(());
+                    }
+                };
+                // in fast descend mode, follow single-child dirs until a branch point
+                while self.fast_descend {
+                    let [child] = tree.nodes.as_slice() else {
+                        break;
+                    };
+                    if !child.is_dir() {
+                        break;
+                    }
+                    let child_path = path.join(child.name());
+                    let child_id = child.subtree.unwrap();
+                    match self.tree_cache.get(self.repo, child_id) {
+                        Ok(child_tree) => {
+                            (path, tree_id, tree) = (child_path, child_id, child_tree);
+                        }
+                        Err(err) => {
+                            // stay in the last dir which could be read
+                            self.show_tree_error(&child_path, &err);
+                            break;
+                        }
+                    }
+                }
+                let parent = std::mem::replace(&mut self.tree, tree);
+                let parent_id = std::mem::replace(&mut self.tree_id, tree_id);
+                let parent_path = std::mem::replace(&mut self.path, path);
+                self.trees.push((parent, parent_id, idx, parent_path));
+                self.filter = None;
+                self.marked.clear();
+                self.sort_tree();
+                self.restore_position();
             }
         }
-        self.table.widget.set_to(0);
         self.update_table();
         Ok(())
     }
 
+    /// Show an error message for the dir at `path` whose tree cannot be read
+    fn show_tree_error(&mut self, path: &Path, err: &anyhow::Error) {
+        warn!("cannot read tree of /{}: {err:?}", path.display());
+        self.current_screen = CurrentScreen::ShowMessage(popup_text(
+            "read dir",
+            format!("cannot read /{}: {err}", path.display()).into(),
+        ));
+    }
+
+    /// Remember the selected node of the current dir
+    fn remember_position(&mut self) {
+        if self.flat.is_some() {
+            return;
+        }
+        if let Some(idx) = self.selected_idx() {
+            _ = self.positions.insert(self.tree_id, idx);
+        }
+    }
+
+    /// Select the node remembered for the current dir or the first row
+    fn restore_position(&mut self) {
+        let row = match self.positions.get(&self.tree_id) {
+            Some(&idx) if idx < self.tree.nodes.len() => self.row_of(idx),
+            Some(_) => {
+                // the dir has less nodes than remembered
+                _ = self.positions.remove(&self.tree_id);
+                0
+            }
+            None => 0,
+        };
+        self.table.widget.set_to(row);
+    }
+
     pub fn goback(&mut self) -> bool {
-        _ = self.path.pop();
-        if let Some((tree, tree_id, idx)) = self.trees.pop() {
+        self.remember_position();
+        self.flat = None;
+        self.filter = None;
+        self.marked.clear();
+        if let Some((tree, tree_id, idx, path)) = self.trees.pop() {
+            self.path = path;
             self.tree = tree;
             self.tree_id = tree_id;
-            self.table.widget.set_to(idx);
+            self.sort_tree();
+            self.table.widget.set_to(self.row_of(idx));
             self.update_table();
             false
         } else {
@@ -213,113 +1102,1895 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
         }
     }
 
-    pub fn toggle_numeric(&mut self) {
-        self.numeric = !self.numeric;
-        self.update_table();
+    /// Whether the dir listing is shown without any popup or other screen on top
+    pub const fn is_browsing(&self) -> bool {
+        matches!(self.current_screen, CurrentScreen::Snapshot)
     }
 
-    pub fn compute_sizes(&mut self) -> Result<()> {
-        let pb = self.repo.progress_bars();
-        let p = pb.progress_counter("computing (sub)-dir information");
-        self.summary_map.compute(self.repo, self.tree_id, &p)?;
-        p.finish();
+    /// Return to the snapshot root at once, selecting the entry which was selected there
+    pub fn goto_root(&mut self) {
+        let Some((tree, tree_id, idx, path)) = self.trees.drain(..).next() else {
+            return;
+        };
+        self.remember_position();
+        self.flat = None;
+        self.filter = None;
+        self.marked.clear();
+        self.path = path;
+        self.tree = tree;
+        self.tree_id = tree_id;
+        self.sort_tree();
+        self.table.widget.set_to(self.row_of(idx));
         self.update_table();
+    }
+
+    /// Enter the dir the symlink `node` in `link_dir` points to, showing an error message if
+    /// the target is not a dir within the snapshot
+    fn enter_symlink(&mut self, node: &Node, link_dir: PathBuf, idx: usize) -> Result<()> {
+        let target = match self.resolve_dir_symlink(node, link_dir) {
+            Ok(Ok((path, tree_id))) => self
+                .tree_cache
+                .get(self.repo, tree_id)
+                .map(|tree| Ok((path, tree_id, tree))),
+            Ok(Err(msg)) => Ok(Err(msg)),
+            Err(err) => Err(err),
+        };
+        match target {
+            Err(err) => {
+                let path = self.path.join(node.name());
+                self.show_tree_error(&path, &err);
+            }
+            Ok(Ok((path, tree_id, tree))) => {
+                self.remember_position();
+                let parent = std::mem::replace(&mut self.tree, tree);
+                let parent_id = std::mem::replace(&mut self.tree_id, tree_id);
+                let parent_path = std::mem::replace(&mut self.path, path);
+                self.trees.push((parent, parent_id, idx, parent_path));
+                self.filter = None;
+                self.marked.clear();
+                self.sort_tree();
+                self.restore_position();
+                self.update_table();
+            }
+            Ok(Err(msg)) => {
+                self.current_screen =
+                    CurrentScreen::ShowMessage(popup_text("follow symlink", msg.into()));
+            }
+        }
         Ok(())
     }
 
-    pub fn input(&mut self, event: Event) -> Result<SnapshotResult> {
-        use KeyCode::{Backspace, Char, Enter, Esc, Left, Right};
-        match &mut self.current_screen {
-            CurrentScreen::Snapshot => match event {
-                Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-                    Enter | Right => self.enter()?,
-                    Backspace | Left => {
-                        if self.goback() {
-                            return Ok(SnapshotResult::Return(std::mem::take(
-                                &mut self.summary_map,
-                            )));
-                        }
-                    }
-                    Esc | Char('q') => {
-                        self.current_screen = CurrentScreen::PromptExit(popup_prompt(
-                            "exit rustic",
-                            "do you want to exit? (y/n)".into(),
-                        ));
-                    }
-                    Char('?') => {
-                        self.current_screen =
-                            CurrentScreen::ShowHelp(popup_text("help", HELP_TEXT.into()));
-                    }
-                    Char('n') => self.toggle_numeric(),
-                    Char('s') => self.compute_sizes()?,
-                    Char('v') => {
-                        // viewing is not supported on cold repositories
-                        if self.repo.config().is_hot != Some(true) {
-                            if let Some(node) = self.selected_node() {
-                                if node.is_file() {
-                                    if let Ok(data) = self.repo.open_file(node)?.read_at(
-                                        self.repo,
-                                        0,
-                                        node.meta.size.min(1_000_000).try_into().unwrap(),
-                                    ) {
-                                        // viewing is only supported for text files
-                                        if let Ok(content) = String::from_utf8(data.to_vec()) {
-                                            let lines = content.lines().count();
-                                            let path = self.path.join(node.name());
-                                            let path = path.display();
-                                            self.current_screen = CurrentScreen::ShowFile(
-                                                Box::new(popup_scrollable_text(
-                                                    format!("{}:/{path}", self.snapshot.id),
-                                                    &content,
-                                                    (lines + 1).min(40).try_into().unwrap(),
-                                                )),
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Char('r') => {
-                        if let Some(node) = self.selected_node() {
-                            let is_absolute = self
-                                .snapshot
-                                .paths
-                                .iter()
-                                .any(|p| Path::new(p).is_absolute());
-                            let path = self.path.join(node.name());
-                            let path = path.display();
-                            let default_target = if is_absolute {
-                                format!("/{path}")
-                            } else {
-                                format!("{path}")
-                            };
-                            let restore = Restore::new(
-                                self.repo,
-                                node.clone(),
-                                format!("{}:/{path}", self.snapshot.id),
-                                &default_target,
-                            );
-                            self.current_screen = CurrentScreen::Restore(Box::new(restore));
-                        }
-                    }
-                    _ => self.table.input(event),
-                },
-                _ => {}
-            },
-            CurrentScreen::ShowFile(prompt) => match prompt.input(event) {
-                TextInputResult::Cancel | TextInputResult::Input(_) => {
-                    self.current_screen = CurrentScreen::Snapshot;
-                }
-                TextInputResult::None => {}
-            },
-            CurrentScreen::ShowHelp(_) => match event {
-                Event::Key(key) if key.kind == KeyEventKind::Press => {
-                    if matches!(key.code, Char('q' | ' ' | '?') | Esc | Enter) {
-                        self.current_screen = CurrentScreen::Snapshot;
-                    }
+    /// Resolve the target of the symlink `node` in `link_dir` to a dir within the snapshot,
+    /// also following symlinks within the target path
+    ///
+    /// Returns the path and tree id of the target dir or an error message.
+    fn resolve_dir_symlink(
+        &mut self,
+        node: &Node,
+        link_dir: PathBuf,
+    ) -> Result<std::result::Result<(PathBuf, TreeId), String>> {
+        let link = node.node_type.to_link().to_path_buf();
+        let mut dir = link_dir;
+        let mut pending = Vec::new();
+        let mut visited = BTreeSet::new();
+        push_link_target(&link, &mut dir, &mut pending);
+        while let Some(name) = pending.pop() {
+            if name == ".." {
+                _ = dir.pop();
+                continue;
+            }
+            let Some((tree, _)) = self.tree_at(&dir)? else {
+                return Ok(Err(format!(
+                    "{}: /{} is not a directory",
+                    link.display(),
+                    dir.display()
+                )));
+            };
+            let Some(child) = tree.nodes.into_iter().find(|node| node.name() == name) else {
+                return Ok(Err(format!(
+                    "{}: /{} does not exist in the snapshot",
+                    link.display(),
+                    dir.join(&name).display()
+                )));
+            };
+            if child.is_symlink() {
+                if !visited.insert(dir.join(&name)) {
+                    return Ok(Err(format!("{}: symlink loop detected", link.display())));
                 }
-                _ => {}
+                push_link_target(child.node_type.to_link(), &mut dir, &mut pending);
+            } else {
+                dir.push(name);
+            }
+        }
+        Ok(match self.tree_at(&dir)? {
+            Some((_, id)) => Ok((dir, id)),
+            None => Err(format!(
+                "{}: /{} is not a directory",
+                link.display(),
+                dir.display()
+            )),
+        })
+    }
+
+    /// The tree and its id at the snapshot `path` or `None` if it is not a dir
+    fn tree_at(&mut self, path: &Path) -> Result<Option<(Tree, TreeId)>> {
+        self.tree_below(self.snapshot.tree, path)
+    }
+
+    /// The tree and its id at `path` below the tree `root` or `None` if it is not a dir
+    fn tree_below(&mut self, root: TreeId, path: &Path) -> Result<Option<(Tree, TreeId)>> {
+        let mut id = root;
+        let mut tree = self.tree_cache.get(self.repo, id)?;
+        for name in path {
+            let Some(subtree) = tree
+                .nodes
+                .iter()
+                .find(|node| node.name() == name)
+                .and_then(|node| node.subtree)
+            else {
+                return Ok(None);
+            };
+            id = subtree;
+            tree = self.tree_cache.get(self.repo, id)?;
+        }
+        Ok(Some((tree, id)))
+    }
+
+    /// Toggle showing only the entries changed since the parent snapshot
+    ///
+    /// Returns a message if the snapshot has no parent.
+    pub fn toggle_changed_since_parent(&mut self) -> Result<Option<String>> {
+        if self.since_parent.take().is_none() {
+            let Some(parent) = self.snapshot.parent else {
+                return Ok(Some("the snapshot has no parent".to_string()));
+            };
+            let parent = self
+                .repo
+                .get_snapshot_from_str(&parent.to_hex(), |_| true)?;
+            self.since_parent = Some(parent.tree);
+        }
+        self.sort_tree();
+        self.update_table();
+        Ok(None)
+    }
+
+    /// The changes of the current dir compared to the same dir below the tree `parent_root`
+    ///
+    /// Only entries which are not identical are contained; if the dir doesn't exist in the parent,
+    /// all entries are new.
+    fn changes_since(&mut self, parent_root: TreeId) -> Result<BTreeMap<OsString, NodeDiff>> {
+        let path = self.path.clone();
+        let parent_tree = self
+            .tree_below(parent_root, &path)?
+            .map(|(tree, _)| tree)
+            .unwrap_or_default();
+        let changes = self
+            .tree
+            .nodes
+            .iter()
+            .filter_map(|node| {
+                let parent_node = parent_tree
+                    .nodes
+                    .iter()
+                    .find(|parent_node| parent_node.name() == node.name());
+                let diff = NodeDiff::from(parent_node, Some(node), |node1, node2| {
+                    node1.content == node2.content && node1.subtree == node2.subtree
+                });
+                (!diff.is_identical()).then(|| (node.name(), diff))
+            })
+            .collect();
+        Ok(changes)
+    }
+
+    /// The current view preferences to keep for the next session
+    fn view_prefs(&self) -> ViewPrefs {
+        ViewPrefs {
+            version: VIEW_PREFS_VERSION,
+            numeric: self.numeric,
+            sort_key: self.sort_key,
+            sort_reverse: self.sort_reverse,
+            dirs_first: self.dirs_first,
+            ignore_case: self.ignore_case,
+            hide_hidden: self.hide_hidden,
+            time_field: self.time_field,
+            hidden_columns: self.hidden_columns.clone(),
+        }
+    }
+
+    pub fn toggle_follow_symlinks(&mut self) {
+        self.follow_symlinks = !self.follow_symlinks;
+        self.update_table();
+    }
+
+    pub fn toggle_flat(&mut self) -> Result<()> {
+        if self.flat.is_some() {
+            _ = self.goback();
+            return Ok(());
+        }
+        let ls_opts = LsOptions::default().recursive(true);
+        match self.flat_tree(&ls_opts, false) {
+            Ok(tree) => self.show_flat(tree, "all files".to_string()),
+            Err(err) => self.show_tree_error(&self.path.clone(), &err),
+        }
+        Ok(())
+    }
+
+    /// Show the given flat tree on top of the current dir
+    fn show_flat(&mut self, tree: Tree, description: String) {
+        let idx = self.selected_idx().unwrap_or_default();
+        // the flat view doesn't change the path
+        self.trees.push((
+            std::mem::replace(&mut self.tree, tree),
+            self.tree_id,
+            idx,
+            self.path.clone(),
+        ));
+        self.flat = Some(description);
+        self.filter = None;
+        self.marked.clear();
+        self.sort_tree();
+        self.table.widget.set_to(0);
+        self.update_table();
+    }
+
+    /// Collect all nodes below the current dir into a single tree with nodes named by their relative path
+    fn flat_tree(&self, ls_opts: &LsOptions, with_dirs: bool) -> Result<Tree> {
+        let pb = self.repo.progress_bars();
+        let p = pb.progress_counter("collecting all files");
+        let mut dir = Node::new_node(OsStr::new(""), NodeType::Dir, Metadata::default());
+        dir.subtree = Some(self.tree_id);
+        let mut nodes = Vec::new();
+        for item in self.repo.ls(&dir, ls_opts)? {
+            let (path, node) = item?;
+            if node.is_dir() && !with_dirs {
+                continue;
+            }
+            nodes.push(flat_node(&path, node));
+            p.inc(1);
+        }
+        p.finish();
+        Ok(Tree { nodes })
+    }
+
+    /// Search all nodes below the current dir whose name contains `query` (ignoring case)
+    ///
+    /// The matches are shown in the flat view, an error message is returned if nothing matches or
+    /// the search fails; then the view is kept. The search can be cancelled by pressing Esc, which
+    /// shows the matches found so far.
+    fn search_recursive(&mut self, query: &str) -> Option<String> {
+        let query_lower = query.to_lowercase();
+        let pb = self.repo.progress_bars();
+        let p = pb.progress_counter(format!("searching for \"{query}\" (Esc to cancel)"));
+        let mut dir = Node::new_node(OsStr::new(""), NodeType::Dir, Metadata::default());
+        dir.subtree = Some(self.tree_id);
+        let ls_opts = LsOptions::default().recursive(true);
+        let result = cancel_on_esc(|cancel| -> Result<_> {
+            let mut nodes = Vec::new();
+            for item in self.repo.ls(&dir, &ls_opts)? {
+                if cancel.load(atomic::Ordering::Relaxed) {
+                    return Ok((nodes, true));
+                }
+                let (path, node) = item?;
+                p.inc(1);
+                if node
+                    .name()
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&query_lower)
+                {
+                    nodes.push(flat_node(&path, node));
+                }
+            }
+            Ok((nodes, false))
+        });
+        p.finish();
+        let (nodes, cancelled) = match result {
+            Ok(found) => found,
+            Err(err) => {
+                warn!("cannot search for \"{query}\": {err:?}");
+                return Some(format!("cannot search for \"{query}\": {err}"));
+            }
+        };
+        let cancelled = if cancelled { " (cancelled)" } else { "" };
+        if nodes.is_empty() {
+            return Some(format!("no match for \"{query}\"{cancelled}"));
+        }
+        // the search was below the dir of the flat view, so replace it
+        if self.flat.is_some() {
+            _ = self.goback();
+        }
+        self.show_flat(
+            Tree { nodes },
+            format!("names containing \"{query}\"{cancelled}"),
+        );
+        None
+    }
+
+    /// Go to the given path relative to the current dir, entering all dirs and selecting a final file
+    fn goto(&mut self, path: &Path) -> Result<()> {
+        let fast_descend = std::mem::replace(&mut self.fast_descend, false);
+        for name in path {
+            let Some(idx) = self.tree.nodes.iter().position(|node| node.name() == name) else {
+                break;
+            };
+            self.table.widget.set_to(self.row_of(idx));
+            if !self.tree.nodes[idx].is_dir() {
+                break;
+            }
+            self.enter()?;
+        }
+        self.fast_descend = fast_descend;
+        self.update_table();
+        Ok(())
+    }
+
+    /// Go to the path matching `glob` or show all matches
+    ///
+    /// Returns an error message if nothing matches
+    fn goto_glob(&mut self, glob: &str) -> Result<Option<String>> {
+        if !glob.contains(['*', '?', '[', '{']) {
+            return self.goto_path(Path::new(glob));
+        }
+        if self.flat.is_some() {
+            _ = self.goback();
+        }
+        if glob.starts_with('/') {
+            while !self.trees.is_empty() {
+                _ = self.goback();
+            }
+        }
+        let ls_opts = LsOptions::default()
+            .recursive(true)
+            .glob(vec![glob.to_string()]);
+        let mut tree = match self.flat_tree(&ls_opts, true) {
+            Ok(tree) => tree,
+            Err(err) => {
+                warn!("cannot list {glob}: {err:?}");
+                return Ok(Some(format!("cannot list {glob}: {err}")));
+            }
+        };
+        match tree.nodes.len() {
+            0 => Ok(Some(format!("no match for {glob}"))),
+            1 => {
+                let path = PathBuf::from(tree.nodes.remove(0).name());
+                self.goto(&path)?;
+                Ok(None)
+            }
+            _ => {
+                self.show_flat(tree, format!("matching {glob}"));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Go to a path (absolute or relative to the current dir)
+    ///
+    /// The path is checked before moving, so an error message is returned and nothing changes
+    /// if a component doesn't exist
+    fn goto_path(&mut self, path: &Path) -> Result<Option<String>> {
+        let absolute = path.has_root();
+        let mut tree = match (absolute, self.trees.first(), self.trees.last()) {
+            (true, Some((root, ..)), _) => root,
+            (false, _, Some((parent, ..))) if self.flat.is_some() => parent,
+            _ => &self.tree,
+        }
+        .clone();
+        let mut names = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => names.push(name),
+                Component::ParentDir => {
+                    return Ok(Some(format!("{}: '..' is not supported", path.display())));
+                }
+                _ => {}
+            }
+        }
+        for (i, name) in names.iter().enumerate() {
+            let Some(node) = tree.nodes.iter().find(|node| node.name() == *name) else {
+                return Ok(Some(format!(
+                    "{}: {} does not exist",
+                    path.display(),
+                    name.to_string_lossy()
+                )));
+            };
+            if i + 1 < names.len() {
+                let Some(subtree) = node.subtree else {
+                    return Ok(Some(format!(
+                        "{}: {} is not a directory",
+                        path.display(),
+                        name.to_string_lossy()
+                    )));
+                };
+                tree = match self.tree_cache.get(self.repo, subtree) {
+                    Ok(tree) => tree,
+                    Err(err) => {
+                        warn!("cannot read tree {}: {err:?}", subtree.to_hex());
+                        return Ok(Some(format!(
+                            "{}: cannot read {}: {err}",
+                            path.display(),
+                            name.to_string_lossy()
+                        )));
+                    }
+                };
+            }
+        }
+
+        if self.flat.is_some() {
+            _ = self.goback();
+        }
+        if absolute {
+            while !self.trees.is_empty() {
+                _ = self.goback();
+            }
+        }
+        self.goto(&names.into_iter().collect::<PathBuf>())?;
+        Ok(None)
+    }
+
+    /// Show the tree with the given (full) `id`, e.g. to inspect trees not reachable from the snapshot
+    ///
+    /// The tree is shown like a subdir of the current dir with `<id>` as path.
+    /// Returns a message if the tree cannot be loaded.
+    fn goto_tree_id(&mut self, id: &str) -> Option<String> {
+        let id = id.trim();
+        let tree_id: TreeId = match Id::from_hex(id) {
+            Ok(id) => id.into(),
+            Err(err) => return Some(format!("{id} is not a valid id: {err}")),
+        };
+        let tree = match self.tree_cache.get(self.repo, tree_id) {
+            Ok(tree) => tree,
+            Err(err) => return Some(format!("cannot load tree {id}: {err}")),
+        };
+        if self.flat.is_some() {
+            _ = self.goback();
+        }
+        self.remember_position();
+        let idx = self.selected_idx().unwrap_or_default();
+        let parent = std::mem::replace(&mut self.tree, tree);
+        let parent_id = std::mem::replace(&mut self.tree_id, tree_id);
+        let parent_path = std::mem::replace(&mut self.path, PathBuf::from(format!("<{tree_id}>")));
+        self.trees.push((parent, parent_id, idx, parent_path));
+        self.filter = None;
+        self.marked.clear();
+        self.sort_tree();
+        self.restore_position();
+        self.update_table();
+        None
+    }
+
+    /// Select the next (or previous) node whose name contains the last search (ignoring case)
+    ///
+    /// If `skip_current` is false, the currently selected node is also considered
+    pub fn jump_to_match(&mut self, forward: bool, skip_current: bool) {
+        let Some(query) = &self.last_search else {
+            return;
+        };
+        let query = query.to_lowercase();
+        let len = self.order.len();
+        let current = self.table.widget.selected().unwrap_or_default();
+        let start = usize::from(skip_current);
+        let found = (start..len + start)
+            .map(|i| {
+                if forward {
+                    (current + i) % len
+                } else {
+                    (current + len - i) % len
+                }
+            })
+            .find(|&row| {
+                self.tree.nodes[self.order[row]]
+                    .name()
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&query)
+            });
+        if let Some(row) = found {
+            self.table.widget.set_to(row);
+        }
+    }
+
+    /// Select the next node whose name starts with `c` (ignoring case), wrapping around
+    pub fn jump_to_initial(&mut self, c: char) {
+        let len = self.order.len();
+        let current = self.table.widget.selected().unwrap_or_default();
+        let c = c.to_lowercase().to_string();
+        let found = (1..=len).map(|i| (current + i) % len).find(|&row| {
+            self.tree.nodes[self.order[row]]
+                .name()
+                .to_string_lossy()
+                .to_lowercase()
+                .starts_with(&c)
+        });
+        if let Some(row) = found {
+            self.table.widget.set_to(row);
+        }
+    }
+
+    pub fn toggle_fast_descend(&mut self) {
+        self.fast_descend = !self.fast_descend;
+        self.update_table();
+    }
+
+    pub fn toggle_ignore_case(&mut self) {
+        self.ignore_case = !self.ignore_case;
+        self.resort();
+    }
+
+    pub fn cycle_sort_key(&mut self) {
+        self.sort_key = self.sort_key.next();
+        self.resort();
+    }
+
+    pub fn toggle_sort_reverse(&mut self) {
+        self.sort_reverse = !self.sort_reverse;
+        self.resort();
+    }
+
+    pub fn toggle_dirs_first(&mut self) {
+        self.dirs_first = !self.dirs_first;
+        self.resort();
+    }
+
+    /// Sort the current tree again, keeping the selected node
+    fn resort(&mut self) {
+        let selected = self.selected_idx();
+        self.sort_tree();
+        if let Some(idx) = selected {
+            self.table.widget.set_to(self.row_of(idx));
+        }
+        self.update_table();
+    }
+
+    pub fn toggle_mark(&mut self) {
+        if let Some(idx) = self.selected_idx() {
+            if !self.marked.remove(&idx) {
+                _ = self.marked.insert(idx);
+            }
+            self.table.widget.next();
+            self.update_table();
+        }
+    }
+
+    /// Set the name filter for the current dir
+    pub fn set_filter(&mut self, filter: Option<String>) {
+        self.filter = filter.filter(|filter| !filter.is_empty());
+        self.sort_tree();
+        self.table.widget.set_to(0);
+        self.update_table();
+    }
+
+    pub fn toggle_full_path(&mut self) {
+        self.full_path = !self.full_path;
+        self.update_table();
+    }
+
+    pub fn toggle_hide_hidden(&mut self) {
+        self.hide_hidden = !self.hide_hidden;
+        self.resort();
+    }
+
+    /// Compute the order in which the nodes of the current tree are shown, leaving out filtered nodes
+    fn sort_tree(&mut self) {
+        self.cache_stored_sizes();
+        self.order = self.sort_order(&self.tree.nodes);
+        // the flat view has no corresponding dir in the parent; if the parent trees cannot be
+        // read, all entries are shown without changes
+        self.changes = self
+            .since_parent
+            .filter(|_| self.flat.is_none())
+            .and_then(|root| self.changes_since(root).ok());
+        if let Some(changes) = &self.changes {
+            self.order
+                .retain(|&idx| changes.contains_key(&self.tree.nodes[idx].name()));
+        }
+        if self.hide_hidden {
+            self.order.retain(|&idx| {
+                !self.tree.nodes[idx]
+                    .name()
+                    .as_encoded_bytes()
+                    .starts_with(b".")
+            });
+        }
+        if let Some(filter) = &self.filter {
+            let filter = filter.to_lowercase();
+            self.order.retain(|&idx| {
+                self.tree.nodes[idx]
+                    .name()
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&filter)
+            });
+        }
+    }
+
+    /// The indices of `nodes` sorted by the current sort settings
+    fn sort_order(&self, nodes: &[Node]) -> Vec<usize> {
+        let mut order: Vec<_> = (0..nodes.len()).collect();
+        order.sort_by(|&i1, &i2| self.cmp_nodes(&nodes[i1], &nodes[i2]));
+        order
+    }
+
+    /// Compare two nodes by the sort column, using the name as tie breaker
+    fn cmp_nodes(&self, node1: &Node, node2: &Node) -> Ordering {
+        let ord = match self.sort_key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => self.node_size(node1).cmp(&self.node_size(node2)),
+            SortKey::Mode => node1.mode_str().cmp(&node2.mode_str()),
+            SortKey::User if self.numeric => node1.meta.uid.cmp(&node2.meta.uid),
+            SortKey::User => node1.meta.user.cmp(&node2.meta.user),
+            SortKey::Group if self.numeric => node1.meta.gid.cmp(&node2.meta.gid),
+            SortKey::Group => node1.meta.group.cmp(&node2.meta.group),
+            SortKey::Time => self
+                .time_field
+                .get(&node1.meta)
+                .cmp(&self.time_field.get(&node2.meta)),
+        }
+        .then_with(|| {
+            cmp_paths(
+                Path::new(&node1.name()),
+                Path::new(&node2.name()),
+                self.ignore_case,
+            )
+        });
+        let ord = if self.sort_reverse {
+            ord.reverse()
+        } else {
+            ord
+        };
+        if self.dirs_first {
+            node2.is_dir().cmp(&node1.is_dir()).then(ord)
+        } else {
+            ord
+        }
+    }
+
+    /// The size of a node, using the computed size for dirs if available
+    ///
+    /// If stored sizes are shown, this is the stored size.
+    fn node_size(&self, node: &Node) -> u64 {
+        if self.show_stored {
+            return self.stored_size(node);
+        }
+        node.subtree
+            .and_then(|id| self.summary_map.get(&id))
+            .map_or(node.meta.size, |sum| sum.summary.size)
+    }
+
+    /// The size of the (deduplicated) contents of a node as stored in the repository
+    ///
+    /// For dirs, this is only known after computing the sizes and is 0 otherwise.
+    fn stored_size(&self, node: &Node) -> u64 {
+        self.stored_sizes.cached(node).unwrap_or_else(|| {
+            BlobInfoRef::from_node_or_map(node, &self.summary_map)
+                .stored_size(self.repo)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Cache the stored sizes of the shown entries if stored sizes are shown, so they are not
+    /// looked up in the index again for sorting and each redraw
+    fn cache_stored_sizes(&mut self) {
+        if !self.show_stored {
+            return;
+        }
+        let frozen_nodes = self.frozen.iter().flat_map(|frozen| &frozen.tree.nodes);
+        for node in self.tree.nodes.iter().chain(frozen_nodes) {
+            _ = self.stored_sizes.get(self.repo, node, &self.summary_map);
+        }
+    }
+
+    pub fn toggle_stored_size(&mut self) {
+        self.show_stored = !self.show_stored;
+        self.resort();
+    }
+
+    pub fn toggle_numeric(&mut self) {
+        self.numeric = !self.numeric;
+        self.resort();
+    }
+
+    /// Start computing the sizes of all (sub)-dirs in the background, which can be cancelled by
+    /// pressing Esc
+    ///
+    /// The sizes are shown as soon as they are computed.
+    pub fn compute_sizes(&mut self) {
+        if self.sizes.is_some() {
+            return;
+        }
+        let (sizes, job) = SummaryJob::new(self.repo, vec![self.tree_id], &self.summary_map);
+        self.sizes = Some(sizes);
+        self.job = Some(job);
+    }
+
+    /// Take the job to start in the background, if any
+    pub fn take_job(&mut self) -> Option<Job<'a>> {
+        if let CurrentScreen::Diff(diff) = &mut self.current_screen {
+            return diff.take_job();
+        }
+        self.job.take()
+    }
+
+    /// Show the sizes computed in the background so far
+    ///
+    /// Returns whether the computation is still running.
+    pub fn update(&mut self) -> bool {
+        if let CurrentScreen::Diff(diff) = &mut self.current_screen {
+            return diff.update();
+        }
+        let Some(sizes) = &mut self.sizes else {
+            return false;
+        };
+        let (merged, finished) = sizes.update(&mut self.summary_map);
+        if merged {
+            self.update_table();
+        }
+        let Some(result) = finished else {
+            return true;
+        };
+        self.sizes = None;
+        self.update_shared_trees();
+        self.resort();
+        let message = match result {
+            Ok(true) => return false,
+            Ok(false) => "cancelled, only sizes of completed dirs are shown".to_string(),
+            Err(err) => {
+                warn!("error computing sizes: {err:?}");
+                format!("error computing sizes, only sizes of completed dirs are shown: {err}")
+            }
+        };
+        // don't replace prompts or popups opened meanwhile
+        if matches!(self.current_screen, CurrentScreen::Snapshot) {
+            self.current_screen =
+                CurrentScreen::ShowMessage(popup_text("compute sizes", message.into()));
+        }
+        false
+    }
+
+    /// Update the trees known to occur more than once, in the whole snapshot if its summary is
+    /// computed or else below the current dir
+    fn update_shared_trees(&mut self) {
+        let root = if self.summary_map.get(&self.snapshot.tree).is_some() {
+            self.snapshot.tree
+        } else {
+            self.tree_id
+        };
+        self.shared_trees = self.summary_map.shared_trees(root);
+    }
+
+    /// All paths in the snapshot sharing the tree of the selected dir, which can be cancelled by
+    /// pressing Esc
+    fn shared_paths(&self) -> Result<Option<PopUpInput>> {
+        let Some((name, id)) = self
+            .selected_node()
+            .and_then(|node| Some((node.name(), node.subtree?)))
+        else {
+            return Ok(None);
+        };
+        let pb = self.repo.progress_bars();
+        let p = pb.progress_counter("searching dirs with identical contents (Esc to cancel)");
+        let mut root = Node::new_node(OsStr::new(""), NodeType::Dir, Metadata::default());
+        root.subtree = Some(self.snapshot.tree);
+        let ls_opts = LsOptions::default().recursive(true);
+        let (paths, cancelled) = cancel_on_esc(|cancel| -> Result<_> {
+            let mut paths = Vec::new();
+            for item in self.repo.ls(&root, &ls_opts)? {
+                if cancel.load(atomic::Ordering::Relaxed) {
+                    return Ok((paths, true));
+                }
+                let (path, node) = item?;
+                p.inc(1);
+                if node.subtree == Some(id) {
+                    paths.push(format!("/{}", path.display()));
+                }
+            }
+            Ok((paths, false))
+        })?;
+        p.finish();
+        let mut text = vec![
+            format!(
+                "{} dirs with the same contents as {} (tree {}){}:",
+                paths.len(),
+                name.to_string_lossy(),
+                id.to_hex(),
+                if cancelled { " (cancelled)" } else { "" }
+            ),
+            String::new(),
+        ];
+        text.extend(paths);
+        let lines = text.len();
+        Ok(Some(popup_scrollable_text(
+            "identical dirs",
+            &text.join("\n"),
+            (lines + 1).min(40).try_into().unwrap(),
+        )))
+    }
+
+    /// Full metadata of the selected node as aligned key/value pairs, including all xattrs
+    fn node_info(&self) -> Option<PopUpInput> {
+        let node = self.selected_node()?;
+        let time = |t: Option<DateTime<Local>>| {
+            t.map_or_else(
+                || "?".to_string(),
+                |t| self.time_zone.format(&t, "%Y-%m-%d %H:%M:%S%.f %z"),
+            )
+        };
+        let opt = |v: Option<u32>| v.map_or_else(|| "?".to_string(), |v| v.to_string());
+        let meta = &node.meta;
+        let mut entries = vec![
+            ("Name", node.name().to_string_lossy().to_string()),
+            ("Type", node.type_str()),
+            ("Mode", node.mode_str()),
+            (
+                "Size",
+                format!("{} ({} bytes)", bytes_size_to_string(meta.size), meta.size),
+            ),
+            ("User", meta.user.clone().unwrap_or_else(|| "?".to_string())),
+            (
+                "Group",
+                meta.group.clone().unwrap_or_else(|| "?".to_string()),
+            ),
+            ("UID", opt(meta.uid)),
+            ("GID", opt(meta.gid)),
+            ("Modified", time(meta.mtime)),
+            ("Accessed", time(meta.atime)),
+            ("Changed", time(meta.ctime)),
+            ("Inode", meta.inode.to_string()),
+            ("Device ID", meta.device_id.to_string()),
+            ("Links", meta.links.to_string()),
+        ];
+        if let Some(device) = node.device_str() {
+            entries.push(("Device", device));
+        }
+        if node.is_symlink() {
+            entries.push((
+                "Target",
+                node.node_type.to_link().to_string_lossy().to_string(),
+            ));
+        }
+        let mut text: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| format!("{key:<12} {value}"))
+            .collect();
+
+        text.push(String::new());
+        if let Some(mode) = meta.mode {
+            text.push("Permissions:".to_string());
+            text.extend(
+                permission_details(mode, node.is_dir())
+                    .into_iter()
+                    .map(|(key, value)| format!("  {key:<10} {value}")),
+            );
+        } else {
+            text.push("no permissions stored".to_string());
+        }
+
+        text.push(String::new());
+        if meta.extended_attributes.is_empty() {
+            text.push("no extended attributes".to_string());
+        } else {
+            text.push("Extended attributes:".to_string());
+            let width = meta
+                .extended_attributes
+                .iter()
+                .map(|attr| attr.name.len())
+                .max()
+                .unwrap_or_default();
+            for attr in &meta.extended_attributes {
+                let value = attr.value.as_deref().map_or_else(
+                    || "(no value)".to_string(),
+                    |value| {
+                        std::str::from_utf8(value).map_or_else(
+                            |_| value.iter().map(|b| format!("{b:02x}")).collect(),
+                            |value| format!("{value:?}"),
+                        )
+                    },
+                );
+                text.push(format!("  {:<width$} {value}", attr.name));
+            }
+        }
+        let lines = text.len();
+        Some(popup_scrollable_text(
+            "details",
+            &text.join("\n"),
+            (lines + 1).min(40).try_into().unwrap(),
+        ))
+    }
+
+    /// Show a bar chart of the sizes of the shown entries of the current dir, largest first
+    ///
+    /// As this needs the sizes of dirs, a message asking to compute them is shown if not done yet.
+    fn show_size_chart(&mut self) {
+        let nodes: Vec<_> = self.order.iter().map(|&i| &self.tree.nodes[i]).collect();
+        if nodes.iter().any(|node| {
+            node.subtree
+                .is_some_and(|id| self.summary_map.get(&id).is_none())
+        }) {
+            self.current_screen = CurrentScreen::ShowMessage(popup_text(
+                "size chart",
+                "sizes of dirs are not computed yet, press s to compute them first".into(),
+            ));
+            return;
+        }
+        if nodes.is_empty() {
+            self.current_screen =
+                CurrentScreen::ShowMessage(popup_text("size chart", "no entries to show".into()));
+            return;
+        }
+        let mut sizes: Vec<_> = nodes
+            .iter()
+            .map(|node| {
+                (
+                    node.name().to_string_lossy().to_string(),
+                    self.node_size(node),
+                )
+            })
+            .collect();
+        sizes.sort_by(|(_, size1), (_, size2)| size2.cmp(size1));
+        let total: u64 = sizes.iter().map(|(_, size)| size).sum();
+        let others = sizes.split_off(sizes.len().min(MAX_CHART_BARS));
+        let mut bars: Vec<_> = sizes
+            .into_iter()
+            .map(|(name, size)| {
+                let name = if name.chars().count() > MAX_CHART_NAME_LEN {
+                    let mut name: String = name.chars().take(MAX_CHART_NAME_LEN - 1).collect();
+                    name.push('…');
+                    name
+                } else {
+                    name
+                };
+                (name, size, bytes_size_to_string(size))
+            })
+            .collect();
+        if !others.is_empty() {
+            let size = others.iter().map(|(_, size)| size).sum();
+            bars.push((
+                format!("({} others)", others.len()),
+                size,
+                bytes_size_to_string(size),
+            ));
+        }
+        let title = format!(
+            "{}sizes in /{} (total {})",
+            if self.show_stored { "stored " } else { "" },
+            self.path.display(),
+            bytes_size_to_string(total)
+        );
+        self.current_screen = CurrentScreen::ShowSizeChart(popup_bar_chart(
+            title,
+            bars,
+            Style::new().fg(tailwind::BLUE.c400),
+        ));
+    }
+
+    /// The content blob ids of the selected file or the subtree id of the selected dir with the
+    /// pack files containing them
+    fn blob_info(&self) -> Option<PopUpInput> {
+        let node = self.selected_node()?;
+        let (kind, entries): (_, Vec<_>) = if let Some(subtree) = node.subtree {
+            (
+                "subtree",
+                vec![(subtree.to_hex(), self.repo.get_index_entry(&subtree))],
+            )
+        } else {
+            (
+                "content blobs",
+                node.content
+                    .iter()
+                    .flatten()
+                    .map(|id| (id.to_hex(), self.repo.get_index_entry(id)))
+                    .collect(),
+            )
+        };
+        let mut packs = BTreeSet::new();
+        let ids: Vec<_> = entries
+            .into_iter()
+            .map(|(id, entry)| match entry {
+                Ok(entry) => {
+                    _ = packs.insert(entry.pack);
+                    format!(
+                        "{id}  pack {}  {:>10} ({} stored)",
+                        entry.pack.to_hex(),
+                        bytes_size_to_string(entry.data_length().into()),
+                        bytes_size_to_string(entry.length.into())
+                    )
+                }
+                Err(_) => format!("{id}  not in index"),
+            })
+            .collect();
+        let mut text = vec![
+            format!(
+                "{}: {} {kind} in {} pack files",
+                node.name().to_string_lossy(),
+                ids.len(),
+                packs.len()
+            ),
+            String::new(),
+        ];
+        text.extend(ids);
+        let lines = text.len();
+        Some(popup_scrollable_text(
+            "blobs",
+            &text.join("\n"),
+            (lines + 1).min(40).try_into().unwrap(),
+        ))
+    }
+
+    /// The marked nodes or the selected node if none is marked
+    fn marked_or_selected(&self) -> Option<Vec<Node>> {
+        if self.marked.is_empty() {
+            Some(vec![self.selected_node()?.clone()])
+        } else {
+            Some(
+                self.marked
+                    .iter()
+                    .map(|&idx| self.tree.nodes[idx].clone())
+                    .collect(),
+            )
+        }
+    }
+
+    /// Whether the snapshot contains absolute paths, i.e. paths can be restored to their original location
+    fn has_absolute_paths(&self) -> bool {
+        self.snapshot
+            .paths
+            .iter()
+            .any(|p| Path::new(p).is_absolute())
+    }
+
+    /// The default restore target for the snapshot `path`, which is absolute if the snapshot paths are
+    fn default_target(&self, path: &Path) -> String {
+        if self.has_absolute_paths() {
+            format!("/{}", path.display())
+        } else {
+            path.display().to_string()
+        }
+    }
+
+    /// The snapshot path of the restored `nodes`: the path of a single node or the current dir
+    fn restore_path(&self, nodes: &[Node]) -> PathBuf {
+        match nodes {
+            [node] => self.path.join(node.name()),
+            _ => self.path.clone(),
+        }
+    }
+
+    /// Restore the marked nodes (or the selected node if none is marked)
+    ///
+    /// Multiple nodes are restored with their names below the current dir.
+    fn restore(&self) -> Option<Restore<'a, P, S>> {
+        let nodes = self.marked_or_selected()?;
+        let path = self.restore_path(&nodes);
+        let default_target = self.default_target(&path);
+        let path = path.display();
+        let source = match nodes.len() {
+            1 => format!("{}:/{path}", self.snapshot.id),
+            n => format!("{n} items from {}:/{path}", self.snapshot.id),
+        };
+        // the total size is only known if the sizes of all dirs are computed
+        let size = nodes
+            .iter()
+            .all(|node| {
+                node.subtree
+                    .is_none_or(|id| self.summary_map.get(&id).is_some())
+            })
+            .then(|| nodes.iter().map(|node| self.node_size(node)).sum());
+        Some(Restore::new(
+            self.repo,
+            nodes,
+            size,
+            source,
+            &default_target,
+        ))
+    }
+
+    /// Write a shell script to `path` with a `rustic restore` command for each marked node
+    /// (or the selected node if none is marked) instead of restoring them
+    ///
+    /// Returns the number of commands written.
+    fn write_restore_script(&self, path: &str) -> Result<usize> {
+        let Some(nodes) = self.marked_or_selected() else {
+            return Ok(0);
+        };
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "#!/bin/sh")?;
+        writeln!(
+            file,
+            "# restore commands for snapshot {}",
+            self.snapshot.id.to_hex()
+        )?;
+        for node in &nodes {
+            let path = self.path.join(node.name());
+            let source = format!("{}:/{}", self.snapshot.id.to_hex(), path.display());
+            writeln!(
+                file,
+                "rustic restore {} {}",
+                shell_quote(&source),
+                shell_quote(&self.default_target(&path))
+            )?;
+        }
+        file.flush()?;
+        Ok(nodes.len())
+    }
+
+    /// Diff the current dir with the same path in the snapshot given by `id`
+    fn diff(&mut self, id: &str) -> Result<Diff<'a, P, S>> {
+        let other = self.repo.get_snapshot_from_str(id, |_| true)?;
+        let path = self.path.to_string_lossy();
+        // the summaries are handed over to the diff, so stop adding to them
+        self.sizes = None;
+        Diff::new(
+            self.repo,
+            self.snapshot.clone(),
+            other,
+            &path,
+            &path,
+            std::mem::take(&mut self.summary_map),
+        )
+    }
+
+    /// Export the shown listing of the current dir to `path`
+    ///
+    /// The listing is written as JSON if `path` ends with `.json`, else as CSV.
+    /// Returns the number of exported entries.
+    fn export_listing(&self, path: &str) -> Result<usize> {
+        let names = header_names(self.show_mime, self.time_field);
+        let rows: Vec<_> = self
+            .order
+            .iter()
+            .map(|&idx| {
+                let mut node = self.tree.nodes[idx].clone();
+                node.meta.size = self.node_size(&node);
+                self.ls_fields(&node, &self.path)
+            })
+            .collect();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        if Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        {
+            let entries: Vec<serde_json::Map<_, _>> = rows
+                .iter()
+                .map(|row| {
+                    names
+                        .iter()
+                        .zip(row)
+                        .map(|(name, value)| (name.to_string(), value.clone().into()))
+                        .collect()
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut file, &entries)?;
+        } else {
+            let header: Vec<_> = names.iter().map(ToString::to_string).collect();
+            for row in once(&header).chain(&rows) {
+                let line: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+                writeln!(file, "{}", line.join(","))?;
+            }
+        }
+        file.flush()?;
+        Ok(rows.len())
+    }
+
+    /// Read the start of the selected file for viewing
+    ///
+    /// Returns `None` if no file is selected.
+    fn view_file(&self) -> Result<Option<FileView>> {
+        let Some(node) = self.selected_node().filter(|node| node.is_file()) else {
+            return Ok(None);
+        };
+        let tui_opts = &RUSTIC_APP.config().tui;
+        let length = node.meta.size.min(tui_opts.max_view_size());
+        let data = read_file(
+            self.repo,
+            node,
+            usize::try_from(length).unwrap_or(usize::MAX),
+            tui_opts.read_chunk_size(),
+        )?;
+        Ok(Some(self.file_view(data, false, false)))
+    }
+
+    /// Show the selected file in the viewer or an error message if it cannot be read
+    fn show_file(&mut self) {
+        self.current_screen = match self.view_file() {
+            Ok(Some(view)) => CurrentScreen::ShowFile(Box::new(view)),
+            Ok(None) => CurrentScreen::Snapshot,
+            Err(err) => {
+                let hint = if self.repo.config().is_hot == Some(true) {
+                    "\n\nFile contents are read from the cold storage, which may not be reachable \
+                     or may need to be made available (warmed up) first."
+                } else {
+                    ""
+                };
+                CurrentScreen::ShowMessage(popup_text(
+                    "view file",
+                    format!("error reading file: {err}{hint}").into(),
+                ))
+            }
+        };
+    }
+
+    /// Show file contents as text or, if forced by `hex` or not valid UTF-8, as hex dump
+    ///
+    /// With `pretty`, text which is valid JSON or TOML is shown reformatted. This is only done
+    /// if the whole file was read, otherwise a message is shown.
+    fn file_view(&self, data: Vec<u8>, hex: bool, pretty: bool) -> FileView {
+        let text = if hex {
+            None
+        } else {
+            std::str::from_utf8(&data).ok().map(str::to_string)
+        };
+        let hex = text.is_none();
+        let node = self.selected_node();
+        let path = self.path.join(node.map(Node::name).unwrap_or_default());
+        let size = node.map_or(0, |node| node.meta.size);
+        let mut message = None;
+        let mut format = None;
+        let content = match text {
+            Some(text) if pretty => {
+                if (data.len() as u64) < size {
+                    message = Some("only files which are shown completely can be pretty-printed");
+                    text
+                } else if let Some((pretty_text, name)) = pretty_print(&path, &text) {
+                    format = Some(name);
+                    pretty_text
+                } else {
+                    message = Some("the content is neither valid JSON nor TOML");
+                    text
+                }
+            }
+            Some(text) => text,
+            None => hex_dump(&data),
+        };
+        let lines = content.lines().count();
+        let truncated = if (data.len() as u64) < size {
+            format!(
+                " (showing first {} of {})",
+                bytes_size_to_string(data.len() as u64),
+                bytes_size_to_string(size)
+            )
+        } else {
+            String::new()
+        };
+        let title = format!(
+            "{}:/{}{}{}{truncated}",
+            self.snapshot.id,
+            path.display(),
+            if hex { " (hex)" } else { "" },
+            format
+                .map(|name| format!(" (formatted {name})"))
+                .unwrap_or_default()
+        );
+        FileView {
+            popup: popup_scrollable_text(
+                title.clone(),
+                &content,
+                (lines + 1).min(40).try_into().unwrap(),
+            ),
+            data,
+            hex,
+            pretty: format.is_some(),
+            title,
+            shown_title: String::new(),
+            query: String::new(),
+            matches: 0,
+            search: None,
+            goto_line: None,
+            copy_choice: None,
+            message: message.map(|msg| popup_text("pretty-print", msg.into())),
+        }
+    }
+
+    /// View, print or open the selected file, showing a message about the outcome if needed
+    fn run_content_action(&mut self, action: ContentAction) {
+        match action {
+            ContentAction::View => self.show_file(),
+            ContentAction::Print => {
+                let msg = match self.print_file() {
+                    Ok(None) => None,
+                    Ok(Some(size)) => {
+                        Some(format!("wrote {} to stdout", bytes_size_to_string(size)))
+                    }
+                    Err(err) => Some(format!("error writing file to stdout: {err}")),
+                };
+                if let Some(msg) = msg {
+                    self.current_screen =
+                        CurrentScreen::ShowMessage(popup_text("print file", msg.into()));
+                }
+            }
+            // fall back to the built-in viewer if no external command is set
+            ContentAction::Open => match external_viewer() {
+                Some(command) => {
+                    if let Err(err) = self.open_external(&command) {
+                        self.current_screen = CurrentScreen::ShowMessage(popup_text(
+                            "open file",
+                            format!("error opening file with {command}: {err}").into(),
+                        ));
+                    }
+                }
+                None => self.show_file(),
+            },
+        }
+    }
+
+    /// Write the full contents of the selected file to stdout, leaving the UI while writing
+    ///
+    /// Returns the number of bytes written or `None` if no file is selected.
+    fn print_file(&self) -> Result<Option<u64>> {
+        let Some(node) = self.selected_node().filter(|node| node.is_file()) else {
+            return Ok(None);
+        };
+        suspend(|| -> Result<()> {
+            let mut stdout = io::stdout().lock();
+            write_file(
+                self.repo,
+                node,
+                node.meta.size.try_into()?,
+                RUSTIC_APP.config().tui.read_chunk_size(),
+                &mut stdout,
+            )?;
+            stdout.flush()?;
+            Ok(())
+        })??;
+        Ok(Some(node.meta.size))
+    }
+
+    /// Open the selected file with the external `command`, leaving the UI while it runs
+    ///
+    /// The full contents are extracted to a temporary file which is removed afterwards.
+    fn open_external(&self, command: &str) -> Result<()> {
+        let Some(node) = self.selected_node().filter(|node| node.is_file()) else {
+            return Ok(());
+        };
+        // keep the file name, so the command can detect the file type
+        let name = node.name();
+        let name = Path::new(&name).file_name().unwrap_or(OsStr::new("file"));
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(name);
+        let mut file = BufWriter::new(File::create(&path)?);
+        write_file(
+            self.repo,
+            node,
+            node.meta.size.try_into()?,
+            RUSTIC_APP.config().tui.read_chunk_size(),
+            &mut file,
+        )?;
+        file.flush()?;
+        drop(file);
+
+        let mut args = command.split_whitespace();
+        let program = args.next().unwrap_or_default();
+        let status = suspend(|| Command::new(program).args(args).arg(&path).status())??;
+        if !status.success() {
+            bail!("{command} exited with {status}");
+        }
+        Ok(())
+    }
+
+    /// Save the full contents of the selected file to the local `path`
+    fn save_file(&self, path: &str) -> Result<u64> {
+        let Some(node) = self.selected_node() else {
+            return Ok(0);
+        };
+        let mut file = BufWriter::new(File::create(path)?);
+        write_file(
+            self.repo,
+            node,
+            node.meta.size.try_into()?,
+            RUSTIC_APP.config().tui.read_chunk_size(),
+            &mut file,
+        )?;
+        file.flush()?;
+        Ok(node.meta.size)
+    }
+
+    pub fn input(&mut self, event: Event) -> Result<SnapshotResult> {
+        use KeyCode::{Backspace, Char, Enter, Esc, Left, Right};
+        match &mut self.current_screen {
+            CurrentScreen::Snapshot => match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                    Enter | Right => self.enter()?,
+                    Backspace | Left => {
+                        if self.goback() {
+                            return Ok(SnapshotResult::Return(std::mem::take(
+                                &mut self.summary_map,
+                            )));
+                        }
+                    }
+                    Char('^') => self.goto_root(),
+                    // while computing sizes, Esc cancels the computation instead of exiting
+                    Esc if self.sizes.is_some() => {
+                        if let Some(sizes) = &self.sizes {
+                            sizes.cancel();
+                        }
+                    }
+                    Esc | Char('q') => {
+                        self.current_screen = CurrentScreen::PromptExit(popup_prompt(
+                            "exit rustic",
+                            "do you want to exit? (y/n)".into(),
+                        ));
+                    }
+                    Char('?') => {
+                        self.current_screen =
+                            CurrentScreen::ShowHelp(popup_text("help", HELP_TEXT.into()));
+                    }
+                    Char('u') => self.toggle_numeric(),
+                    Char('K') => {
+                        self.current_screen = CurrentScreen::PickColumns(self.column_picker());
+                    }
+                    Char('t') => self.cycle_time_field(),
+                    Char('/') => {
+                        self.current_screen = CurrentScreen::Search(popup_input(
+                            "search",
+                            "enter part of name",
+                            self.last_search.as_deref().unwrap_or_default(),
+                            1,
+                        ));
+                    }
+                    Char('f' | 'F') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.current_screen = CurrentScreen::SearchRecursive(popup_input(
+                            "search below current dir",
+                            "enter part of name",
+                            "",
+                            1,
+                        ));
+                    }
+                    Char('f') => {
+                        self.current_screen = CurrentScreen::Filter(popup_input(
+                            "filter",
+                            "enter part of name",
+                            self.filter.as_deref().unwrap_or_default(),
+                            1,
+                        ));
+                    }
+                    Char('n') => self.jump_to_match(true, true),
+                    Char('N') => self.jump_to_match(false, true),
+                    Char('F') => self.toggle_flat()?,
+                    Char('C') => self.toggle_split(),
+                    Char('M') => self.toggle_mime(),
+                    Char('I') => {
+                        if let Some(popup) = self.node_info() {
+                            self.current_screen = CurrentScreen::ShowNodeInfo(popup);
+                        }
+                    }
+                    Char('B') => {
+                        if let Some(popup) = self.blob_info() {
+                            self.current_screen = CurrentScreen::ShowBlobs(popup);
+                        }
+                    }
+                    Char('U') => self.show_size_chart(),
+                    Char('=') => match self.shared_paths() {
+                        Ok(Some(popup)) => {
+                            self.current_screen = CurrentScreen::ShowSharedPaths(popup);
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            warn!("cannot search dirs with identical contents: {err:?}");
+                            let msg = format!("cannot search dirs with identical contents: {err}");
+                            self.current_screen = CurrentScreen::ShowMessage(popup_text(
+                                "identical dirs",
+                                msg.into(),
+                            ));
+                        }
+                    },
+                    Char('T') => {
+                        self.current_screen = CurrentScreen::GotoTree(popup_input(
+                            "go to tree",
+                            "enter tree id",
+                            "",
+                            1,
+                        ));
+                    }
+                    Char('g') => {
+                        self.current_screen = CurrentScreen::GotoPath(popup_input(
+                            "go to",
+                            "enter path or glob",
+                            "",
+                            1,
+                        ));
+                    }
+                    Char('c') => self.toggle_fast_descend(),
+                    Char('L') => self.toggle_follow_symlinks(),
+                    Char('H') => self.toggle_hide_hidden(),
+                    Char('A') => self.toggle_full_path(),
+                    Char('P') => {
+                        if let Some(msg) = self.toggle_changed_since_parent()? {
+                            self.current_screen = CurrentScreen::ShowMessage(popup_text(
+                                "changed since parent",
+                                msg.into(),
+                            ));
+                        }
+                    }
+                    Char('S') => self.toggle_stored_size(),
+                    Char('i') => self.toggle_ignore_case(),
+                    Char('o') => self.cycle_sort_key(),
+                    Char('O') => self.toggle_sort_reverse(),
+                    Char('d') => self.toggle_dirs_first(),
+                    Char('s') => self.compute_sizes(),
+                    Char(c @ ('v' | 'p' | 'V')) => {
+                        let action = match c {
+                            'v' => ContentAction::View,
+                            'p' => ContentAction::Print,
+                            _ => ContentAction::Open,
+                        };
+                        // reading from the cold storage may take a while or incur costs, so ask first
+                        if self.repo.config().is_hot == Some(true)
+                            && !self.cold_read_confirmed
+                            && self.selected_node().is_some_and(Node::is_file)
+                        {
+                            self.current_screen = CurrentScreen::PromptViewCold(
+                                popup_prompt(
+                                    "read file",
+                                    "File contents are read from the cold storage, which may \
+                                     incur retrieval delays or costs.\nDo you want to proceed? (y/n)"
+                                        .into(),
+                                ),
+                                action,
+                            );
+                        } else {
+                            self.run_content_action(action);
+                        }
+                    }
+                    Char(' ') => self.toggle_mark(),
+                    Char('e') => {
+                        self.current_screen = CurrentScreen::Export(popup_input(
+                            "export listing to:",
+                            "enter local path",
+                            "listing.csv",
+                            1,
+                        ));
+                    }
+                    Char('y') => {
+                        if let Some(node) = self.selected_node() {
+                            let path = self.path.join(node.name());
+                            let path = format!("{}:/{}", self.snapshot.id, path.display());
+                            let msg = match copy_to_clipboard(&path) {
+                                Ok(Copied::System) => format!("copied to clipboard:\n\n{path}"),
+                                // the terminal may silently ignore the request, so show the path
+                                Ok(Copied::Terminal) => format!(
+                                    "sent to the terminal to copy it to the clipboard, which \
+                                     may not be supported (OSC 52); path is:\n\n{path}"
+                                ),
+                                Err(err) => {
+                                    format!("cannot copy to clipboard ({err}), path is:\n\n{path}")
+                                }
+                            };
+                            self.current_screen =
+                                CurrentScreen::ShowMessage(popup_text("copy path", msg.into()));
+                        }
+                    }
+                    Char('D') => {
+                        self.current_screen = CurrentScreen::GetDiffSnapshot(popup_input(
+                            "diff with snapshot",
+                            "enter snapshot id",
+                            "",
+                            1,
+                        ));
+                    }
+                    Char('R') => {
+                        self.current_screen = CurrentScreen::RestoreScript(popup_input(
+                            "write restore script to:",
+                            "enter local path",
+                            "restore.sh",
+                            1,
+                        ));
+                    }
+                    // restore to the original location, only asking for confirmation
+                    Char('r')
+                        if key.modifiers == KeyModifiers::CONTROL && self.has_absolute_paths() =>
+                    {
+                        if let Some(mut restore) = self.restore() {
+                            let nodes = self.marked_or_selected().unwrap_or_default();
+                            restore.restore_to(self.default_target(&self.restore_path(&nodes)))?;
+                            self.current_screen = CurrentScreen::Restore(Box::new(restore));
+                        }
+                    }
+                    Char('r') => {
+                        if let Some(restore) = self.restore() {
+                            self.current_screen = CurrentScreen::Restore(Box::new(restore));
+                        }
+                    }
+                    // type-ahead for all letters and digits which are not bound to a command
+                    Char(c)
+                        if c.is_alphanumeric()
+                            && !key
+                                .modifiers
+                                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                    {
+                        self.jump_to_initial(c);
+                    }
+                    _ => self.table.input(event),
+                },
+                _ => {}
+            },
+            CurrentScreen::ShowFile(view) => {
+                if let Some(prompt) = &mut view.search {
+                    match prompt.input(event) {
+                        TextInputResult::Cancel => view.search = None,
+                        TextInputResult::Input(query) => {
+                            view.search = None;
+                            view.search_for(&query);
+                        }
+                        TextInputResult::None => {}
+                    }
+                    return Ok(SnapshotResult::None);
+                }
+                if let Some(prompt) = &mut view.goto_line {
+                    match prompt.input(event) {
+                        TextInputResult::Cancel => view.goto_line = None,
+                        TextInputResult::Input(line) => {
+                            view.goto_line = None;
+                            match line.trim().parse::<usize>() {
+                                Ok(line) => view.popup.0.widget.goto_line(line.saturating_sub(1)),
+                                Err(_) => {
+                                    view.message = Some(popup_text(
+                                        "go to line",
+                                        format!("invalid line number: {line}").into(),
+                                    ));
+                                }
+                            }
+                        }
+                        TextInputResult::None => {}
+                    }
+                    return Ok(SnapshotResult::None);
+                }
+                if view.copy_choice.is_some() {
+                    if let Event::Key(key) = event {
+                        if key.kind == KeyEventKind::Press {
+                            match key.code {
+                                Char('a') => view.copy(true),
+                                Char('p') => view.copy(false),
+                                _ => {}
+                            }
+                            view.copy_choice = None;
+                        }
+                    }
+                    return Ok(SnapshotResult::None);
+                }
+                if view.message.is_some() {
+                    if matches!(event, Event::Key(key) if key.kind == KeyEventKind::Press) {
+                        view.message = None;
+                    }
+                    return Ok(SnapshotResult::None);
+                }
+                match event {
+                    Event::Key(key)
+                        if key.kind == KeyEventKind::Press
+                            && matches!(
+                                key.code,
+                                Char('h' | 'f' | 'w' | 'l' | '/' | ':' | 'n' | 'N' | 'y')
+                            ) =>
+                    {
+                        match key.code {
+                            Char('h') => {
+                                let data = std::mem::take(&mut view.data);
+                                let hex = !view.hex;
+                                self.current_screen = CurrentScreen::ShowFile(Box::new(
+                                    self.file_view(data, hex, false),
+                                ));
+                            }
+                            Char('f') => {
+                                let data = std::mem::take(&mut view.data);
+                                let pretty = !view.pretty;
+                                self.current_screen = CurrentScreen::ShowFile(Box::new(
+                                    self.file_view(data, false, pretty),
+                                ));
+                            }
+                            Char('w') => {
+                                if let Some(node) = self.selected_node() {
+                                    let default_path = Path::new(&node.name())
+                                        .file_name()
+                                        .unwrap_or_default()
+                                        .to_string_lossy()
+                                        .to_string();
+                                    self.current_screen = CurrentScreen::SaveFile(popup_input(
+                                        "save file to:",
+                                        "enter local path",
+                                        &default_path,
+                                        1,
+                                    ));
+                                }
+                            }
+                            Char('l') => view.popup.0.widget.toggle_line_numbers(),
+                            Char('/') => {
+                                view.search =
+                                    Some(popup_input("search", "enter text", &view.query, 1));
+                            }
+                            Char('y') => {
+                                // for small files there is nothing to choose
+                                if view.popup.0.widget.fully_visible() {
+                                    view.copy(true);
+                                } else {
+                                    view.copy_choice = Some(popup_text(
+                                        "copy content",
+                                        "copy (a)ll loaded content or only the visible (p)age?"
+                                            .into(),
+                                    ));
+                                }
+                            }
+                            Char(':') => {
+                                view.goto_line =
+                                    Some(popup_input("go to line", "enter line number", "", 1));
+                            }
+                            Char('n') => view.popup.0.widget.next_match(true),
+                            _ => view.popup.0.widget.next_match(false),
+                        }
+                    }
+                    _ => match view.popup.input(event) {
+                        TextInputResult::Cancel | TextInputResult::Input(_) => {
+                            self.current_screen = CurrentScreen::Snapshot;
+                        }
+                        TextInputResult::None => {}
+                    },
+                }
+            }
+            CurrentScreen::Search(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(query) => {
+                    self.last_search = (!query.is_empty()).then_some(query);
+                    self.jump_to_match(true, false);
+                    self.current_screen = CurrentScreen::Snapshot;
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::Filter(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => {
+                    self.set_filter(None);
+                    self.current_screen = CurrentScreen::Snapshot;
+                }
+                TextInputResult::Input(filter) => {
+                    self.set_filter(Some(filter));
+                    self.current_screen = CurrentScreen::Snapshot;
+                }
+                TextInputResult::None => {
+                    // filter while typing
+                    let filter = prompt.0.widget.text();
+                    self.set_filter(Some(filter));
+                }
+            },
+            CurrentScreen::GotoPath(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(glob) => {
+                    self.current_screen = match self.goto_glob(&glob)? {
+                        Some(msg) => CurrentScreen::ShowMessage(popup_text("go to", msg.into())),
+                        None => CurrentScreen::Snapshot,
+                    };
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::SearchRecursive(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(query) => {
+                    self.current_screen = match self.search_recursive(&query) {
+                        Some(msg) => CurrentScreen::ShowMessage(popup_text("search", msg.into())),
+                        None => CurrentScreen::Snapshot,
+                    };
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::GotoTree(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(id) => {
+                    self.current_screen = match self.goto_tree_id(&id) {
+                        Some(msg) => {
+                            CurrentScreen::ShowMessage(popup_text("go to tree", msg.into()))
+                        }
+                        None => CurrentScreen::Snapshot,
+                    };
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::RestoreScript(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(path) => {
+                    let msg = match self.write_restore_script(&path) {
+                        Ok(count) => format!("wrote {count} restore commands to {path}"),
+                        Err(err) => format!("error writing restore script to {path}: {err}"),
+                    };
+                    self.current_screen =
+                        CurrentScreen::ShowMessage(popup_text("restore script", msg.into()));
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::Export(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(path) => {
+                    let msg = match self.export_listing(&path) {
+                        Ok(count) => format!("exported {count} entries to {path}"),
+                        Err(err) => format!("error exporting to {path}: {err}"),
+                    };
+                    self.current_screen =
+                        CurrentScreen::ShowMessage(popup_text("export listing", msg.into()));
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::SaveFile(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(path) => {
+                    let msg = match self.save_file(&path) {
+                        Ok(size) => {
+                            format!("saved {} to {path}", bytes_size_to_string(size))
+                        }
+                        Err(err) => format!("error saving to {path}: {err}"),
+                    };
+                    self.current_screen =
+                        CurrentScreen::ShowMessage(popup_text("save file", msg.into()));
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::PickColumns(_) => match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                    Char(c @ '1'..='6') => {
+                        let idx = c.to_digit(10).unwrap_or_default() as usize - 1;
+                        self.toggle_column(Column::ALL[idx]);
+                        self.current_screen = CurrentScreen::PickColumns(self.column_picker());
+                    }
+                    Char('q' | 'K') | Esc | Enter => self.current_screen = CurrentScreen::Snapshot,
+                    _ => {}
+                },
+                _ => {}
+            },
+            CurrentScreen::ShowSizeChart(_) => {
+                if matches!(event, Event::Key(key) if key.kind == KeyEventKind::Press) {
+                    self.current_screen = CurrentScreen::Snapshot;
+                }
+            }
+            CurrentScreen::ShowSharedPaths(popup) => match popup.input(event) {
+                TextInputResult::Cancel | TextInputResult::Input(_) => {
+                    self.current_screen = CurrentScreen::Snapshot;
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::ShowBlobs(popup) => match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press && key.code == Char('B') => {
+                    self.current_screen = CurrentScreen::Snapshot;
+                }
+                _ => match popup.input(event) {
+                    TextInputResult::Cancel | TextInputResult::Input(_) => {
+                        self.current_screen = CurrentScreen::Snapshot;
+                    }
+                    TextInputResult::None => {}
+                },
+            },
+            CurrentScreen::ShowNodeInfo(popup) => match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press && key.code == Char('I') => {
+                    self.current_screen = CurrentScreen::Snapshot;
+                }
+                _ => match popup.input(event) {
+                    TextInputResult::Cancel | TextInputResult::Input(_) => {
+                        self.current_screen = CurrentScreen::Snapshot;
+                    }
+                    TextInputResult::None => {}
+                },
+            },
+            CurrentScreen::ShowHelp(_) | CurrentScreen::ShowMessage(_) => match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if matches!(key.code, Char('q' | ' ' | '?') | Esc | Enter) {
+                        self.current_screen = CurrentScreen::Snapshot;
+                    }
+                }
+                _ => {}
+            },
+            CurrentScreen::GetDiffSnapshot(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                TextInputResult::Input(id) => {
+                    self.current_screen = match self.diff(&id) {
+                        Ok(diff) => CurrentScreen::Diff(Box::new(diff)),
+                        Err(err) => CurrentScreen::ShowMessage(popup_text(
+                            "diff",
+                            format!("cannot diff with {id}: {err}").into(),
+                        )),
+                    };
+                }
+                TextInputResult::None => {}
+            },
+            CurrentScreen::Diff(diff) => match diff.input(event)? {
+                DiffResult::Exit => return Ok(SnapshotResult::Exit),
+                DiffResult::Return(summary_map) => {
+                    self.summary_map = summary_map;
+                    self.current_screen = CurrentScreen::Snapshot;
+                    self.update_table();
+                }
+                DiffResult::None => {}
             },
             CurrentScreen::Restore(restore) => {
                 if restore.input(event)? {
@@ -327,7 +2998,21 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
                 }
             }
             CurrentScreen::PromptExit(prompt) => match prompt.input(event) {
-                PromptResult::Ok => return Ok(SnapshotResult::Exit),
+                PromptResult::Ok => {
+                    // failing to store the view preferences shouldn't prevent exiting
+                    _ = self.view_prefs().save();
+                    return Ok(SnapshotResult::Exit);
+                }
+                PromptResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
+                PromptResult::None => {}
+            },
+            CurrentScreen::PromptViewCold(prompt, action) => match prompt.input(event) {
+                PromptResult::Ok => {
+                    let action = *action;
+                    self.current_screen = CurrentScreen::Snapshot;
+                    self.cold_read_confirmed = true;
+                    self.run_content_action(action);
+                }
                 PromptResult::Cancel => self.current_screen = CurrentScreen::Snapshot,
                 PromptResult::None => {}
             },
@@ -335,30 +3020,480 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshot<'a, P, S> {
         Ok(SnapshotResult::None)
     }
 
+    /// The current path as segments separated by `/`, with the current dir highlighted
+    fn breadcrumb(&self) -> Line<'static> {
+        let segment = Style::new().fg(tailwind::BLUE.c400);
+        let current = Style::new()
+            .fg(tailwind::SLATE.c950)
+            .bg(tailwind::BLUE.c400)
+            .add_modifier(Modifier::BOLD);
+        let names: Vec<_> = self
+            .path
+            .iter()
+            .map(|name| name.to_string_lossy().to_string())
+            .collect();
+        let mut spans = vec![Span::raw(format!(" {}:", self.snapshot.id))];
+        spans.push(Span::styled(
+            "/",
+            if names.is_empty() { current } else { segment },
+        ));
+        for (i, name) in names.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw("/"));
+            }
+            let style = if i + 1 == names.len() {
+                current
+            } else {
+                segment
+            };
+            spans.push(Span::styled(format!(" {name} "), style));
+        }
+        Line::from(spans)
+    }
+
     pub fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
-        let rects = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).split(area);
+        let rects = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+        if let CurrentScreen::Diff(diff) = &mut self.current_screen {
+            diff.draw(area, f);
+            return;
+        }
 
         if let CurrentScreen::Restore(restore) = &mut self.current_screen {
             restore.draw(area, f);
         } else {
-            // draw the table
-            self.table.draw(rects[0], f);
-
-            // draw the footer
             let buffer_bg = tailwind::SLATE.c950;
             let row_fg = tailwind::SLATE.c200;
-            let info_footer = Paragraph::new(Line::from(INFO_TEXT))
+
+            // draw the breadcrumb
+            let breadcrumb =
+                Paragraph::new(self.breadcrumb()).style(Style::new().fg(row_fg).bg(buffer_bg));
+            f.render_widget(breadcrumb, rects[0]);
+
+            // draw the table (next to the frozen table in split view)
+            if let Some(frozen) = &mut self.frozen {
+                let panes =
+                    Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(rects[1]);
+                frozen.table.draw(panes[0], f);
+                self.table.draw(panes[1], f);
+            } else {
+                self.table.draw(rects[1], f);
+            }
+
+            // draw the footer, showing the progress while computing sizes
+            let footer = self
+                .sizes
+                .as_ref()
+                .map_or_else(|| Line::from(INFO_TEXT), |sizes| Line::from(sizes.status()));
+            let info_footer = Paragraph::new(footer)
                 .style(Style::new().fg(row_fg).bg(buffer_bg))
                 .centered();
-            f.render_widget(info_footer, rects[1]);
+            f.render_widget(info_footer, rects[2]);
         }
 
         // draw popups
         match &mut self.current_screen {
-            CurrentScreen::Snapshot | CurrentScreen::Restore(_) => {}
+            CurrentScreen::Snapshot | CurrentScreen::Restore(_) | CurrentScreen::Diff(_) => {}
             CurrentScreen::ShowHelp(popup) => popup.draw(area, f),
-            CurrentScreen::PromptExit(popup) => popup.draw(area, f),
-            CurrentScreen::ShowFile(popup) => popup.draw(area, f),
+            CurrentScreen::PromptExit(popup) | CurrentScreen::PromptViewCold(popup, _) => {
+                popup.draw(area, f);
+            }
+            CurrentScreen::ShowFile(view) => view.draw(area, f),
+            CurrentScreen::SaveFile(popup)
+            | CurrentScreen::Export(popup)
+            | CurrentScreen::RestoreScript(popup)
+            | CurrentScreen::GetDiffSnapshot(popup)
+            | CurrentScreen::GotoPath(popup)
+            | CurrentScreen::GotoTree(popup)
+            | CurrentScreen::SearchRecursive(popup)
+            | CurrentScreen::Search(popup)
+            | CurrentScreen::Filter(popup) => popup.draw(area, f),
+            CurrentScreen::ShowMessage(popup) | CurrentScreen::PickColumns(popup) => {
+                popup.draw(area, f);
+            }
+            CurrentScreen::ShowSizeChart(popup) => popup.draw(area, f),
+            CurrentScreen::ShowNodeInfo(popup)
+            | CurrentScreen::ShowBlobs(popup)
+            | CurrentScreen::ShowSharedPaths(popup) => {
+                popup.draw(area, f);
+            }
+        }
+    }
+}
+
+/// The header of the ls table, leaving out the hidden columns
+fn header(with_mime: bool, time_field: TimeField, hidden: &BTreeSet<Column>) -> Vec<Text<'static>> {
+    once("")
+        .chain(visible_columns(header_names(with_mime, time_field), hidden))
+        .map(Text::from)
+        .collect()
+}
+
+/// Leave out the `hidden` columns from `fields`, which are in the order of [`Snapshot::ls_fields`]
+///
+/// Fields after the hideable columns (like the content type) are always kept.
+fn visible_columns<T>(fields: impl IntoIterator<Item = T>, hidden: &BTreeSet<Column>) -> Vec<T> {
+    fields
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            Column::ALL
+                .get(*i)
+                .is_none_or(|column| !hidden.contains(column))
+        })
+        .map(|(_, field)| field)
+        .collect()
+}
+
+/// The names of the columns filled by [`Snapshot::ls_fields`]
+fn header_names(with_mime: bool, time_field: TimeField) -> Vec<&'static str> {
+    ["Name", "Size", "Mode", "User", "Group", time_field.title()]
+        .into_iter()
+        .chain(with_mime.then_some("Type"))
+        .collect()
+}
+
+/// Split `text` into spans where all (non-overlapping) occurrences of `query` are styled with `style`
+///
+/// `query` must be lowercase; the comparison ignores case like the filter of the listing.
+fn highlight_matches(text: &str, query: &str, style: Style) -> Line<'static> {
+    if query.is_empty() {
+        return Line::from(text.to_string());
+    }
+    // lowercasing may change the length of chars, so keep the offset into `text` for each byte
+    let mut lower = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    for (idx, c) in text.char_indices() {
+        lower.extend(c.to_lowercase());
+        offsets.resize(lower.len(), idx);
+    }
+    offsets.push(text.len());
+
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, matched) in lower.match_indices(query) {
+        let (start, end) = (offsets[start], offsets[start + matched.len()]);
+        if start < last || end <= start {
+            continue;
+        }
+        if start > last {
+            spans.push(Span::raw(text[last..start].to_string()));
+        }
+        spans.push(Span::styled(text[start..end].to_string(), style));
+        last = end;
+    }
+    if last < text.len() {
+        spans.push(Span::raw(text[last..].to_string()));
+    }
+    Line::from(spans)
+}
+
+/// The external command to view files, taken from `$PAGER` or `$EDITOR`
+fn external_viewer() -> Option<String> {
+    ["PAGER", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|command| !command.trim().is_empty())
+}
+
+/// Add the components of a symlink target to the `pending` stack of names to resolve
+///
+/// An absolute target is resolved from the snapshot root.
+fn push_link_target(target: &Path, dir: &mut PathBuf, pending: &mut Vec<OsString>) {
+    if target.has_root() {
+        dir.clear();
+    }
+    let names = target
+        .components()
+        .rev()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_os_string()),
+            Component::ParentDir => Some(OsString::from("..")),
+            _ => None,
+        });
+    pending.extend(names);
+}
+
+/// Quote a string for use as a single shell argument
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Quote a CSV field if needed
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The permissions of `mode` as symbolic string, octal number and the permissions of user, group
+/// and others with the special bits explained
+fn permission_details(mode: u32, is_dir: bool) -> Vec<(&'static str, String)> {
+    let perms = |bits: u32| {
+        let perms: Vec<_> = [(4, "read"), (2, "write"), (1, "execute")]
+            .into_iter()
+            .filter(|(bit, _)| bits & bit != 0)
+            .map(|(_, name)| name)
+            .collect();
+        if perms.is_empty() {
+            "none".to_string()
+        } else {
+            perms.join("+")
+        }
+    };
+    let special: Vec<_> = [
+        (0o4000, "setuid (runs with the privileges of the owner)"),
+        (
+            0o2000,
+            if is_dir {
+                "setgid (new entries inherit the group)"
+            } else {
+                "setgid (runs with the privileges of the group)"
+            },
+        ),
+        (
+            0o1000,
+            if is_dir {
+                "sticky (only owners can delete or rename entries)"
+            } else {
+                "sticky"
+            },
+        ),
+    ]
+    .into_iter()
+    .filter(|(bit, _)| mode & bit != 0)
+    .map(|(_, name)| name)
+    .collect();
+    vec![
+        ("symbolic", symbolic_permissions(mode)),
+        ("octal", format!("{:04o}", mode & 0o7777)),
+        ("user", perms((mode >> 6) & 7)),
+        ("group", perms((mode >> 3) & 7)),
+        ("other", perms(mode & 7)),
+        (
+            "special",
+            if special.is_empty() {
+                "none".to_string()
+            } else {
+                special.join(", ")
+            },
+        ),
+    ]
+}
+
+/// The permissions of `mode` like `ls -l`, including setuid, setgid and sticky bits
+fn symbolic_permissions(mode: u32) -> String {
+    let triplet = |bits: u32, special: bool, special_char: char| {
+        let exec = match (bits & 1 != 0, special) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        };
+        [
+            if bits & 4 != 0 { 'r' } else { '-' },
+            if bits & 2 != 0 { 'w' } else { '-' },
+            exec,
+        ]
+    };
+    [
+        triplet((mode >> 6) & 7, mode & 0o4000 != 0, 's'),
+        triplet((mode >> 3) & 7, mode & 0o2000 != 0, 's'),
+        triplet(mode & 7, mode & 0o1000 != 0, 't'),
+    ]
+    .iter()
+    .flatten()
+    .collect()
+}
+
+/// The names of all nodes within a tree
+fn names(tree: &Tree) -> BTreeSet<OsString> {
+    tree.nodes.iter().map(Node::name).collect()
+}
+
+/// Reformat `text` with indentation if it is valid JSON or, for `.toml` files, valid TOML
+///
+/// Returns the reformatted text and the name of the format or `None` if `text` cannot be parsed.
+fn pretty_print(path: &Path, text: &str) -> Option<(String, &'static str)> {
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(text) {
+        return serde_json::to_string_pretty(&value)
+            .ok()
+            .map(|text| (text, "JSON"));
+    }
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+    {
+        let value: toml::Table = toml::from_str(text).ok()?;
+        return toml::to_string_pretty(&value)
+            .ok()
+            .map(|text| (text, "TOML"));
+    }
+    None
+}
+
+/// Format data as hex dump with offset, 16 bytes in hex and the printable ASCII characters
+fn hex_dump(data: &[u8]) -> String {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: String = chunk.iter().map(|b| format!("{b:02x} ")).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        char::from(b)
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}  {hex:<48} |{ascii}|", i * 16)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Read up to `length` bytes from the start of a file, requesting `chunk_size` bytes per read
+fn read_file<P, S: IndexedFull>(
+    repo: &Repository<P, S>,
+    node: &Node,
+    length: usize,
+    chunk_size: usize,
+) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(length);
+    write_file(repo, node, length, chunk_size, &mut data)?;
+    Ok(data)
+}
+
+/// Write up to `length` bytes from the start of a file to `w`, requesting `chunk_size` bytes per read
+fn write_file<P, S: IndexedFull>(
+    repo: &Repository<P, S>,
+    node: &Node,
+    length: usize,
+    chunk_size: usize,
+    w: &mut impl Write,
+) -> Result<()> {
+    let open_file = repo.open_file(node)?;
+    let mut offset = 0;
+    while offset < length {
+        let chunk = open_file.read_at(repo, offset, chunk_size.min(length - offset))?;
+        if chunk.is_empty() {
+            break;
         }
+        w.write_all(&chunk)?;
+        offset += chunk.len();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    /// The text of all spans and whether they are highlighted
+    fn spans(line: &Line<'_>, style: Style) -> Vec<(String, bool)> {
+        line.spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style == style))
+            .collect()
+    }
+
+    #[rstest]
+    #[case("foo.txt", "", &[("foo.txt", false)])]
+    #[case("foo.txt", "bar", &[("foo.txt", false)])]
+    #[case("foo.txt", "foo.txt", &[("foo.txt", true)])]
+    #[case("abcabc", "b", &[("a", false), ("b", true), ("ca", false), ("b", true), ("c", false)])]
+    #[case("aaaa", "aa", &[("aa", true), ("aa", true)])]
+    #[case("aaa", "aa", &[("aa", true), ("a", false)])]
+    #[case("FooBAR", "bar", &[("Foo", false), ("BAR", true)])]
+    #[case("ÄRGER.txt", "ärger", &[("ÄRGER", true), (".txt", false)])]
+    // "İ" is lowercased to "i̇", which is longer than "İ"
+    #[case("xİy", "y", &[("xİ", false), ("y", true)])]
+    #[case("aİb", "i\u{307}", &[("a", false), ("İ", true), ("b", false)])]
+    #[case("aİbİ", "i\u{307}b", &[("a", false), ("İb", true), ("İ", false)])]
+    // only a part of a lowercased char matches, which can't be highlighted
+    #[case("aİb", "i", &[("aİb", false)])]
+    fn highlight_matches_passes(
+        #[case] text: &str,
+        #[case] query: &str,
+        #[case] expected: &[(&str, bool)],
+    ) {
+        let style = Style::new().bold();
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(text, highlighted)| ((*text).to_string(), *highlighted))
+            .collect();
+        assert_eq!(
+            spans(&highlight_matches(text, query, style), style),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(0, "---------")]
+    #[case(0o644, "rw-r--r--")]
+    #[case(0o755, "rwxr-xr-x")]
+    #[case(0o100_644, "rw-r--r--")]
+    #[case(0o4755, "rwsr-xr-x")]
+    #[case(0o4644, "rwSr--r--")]
+    #[case(0o2750, "rwxr-s---")]
+    #[case(0o2740, "rwxr-S---")]
+    #[case(0o1777, "rwxrwxrwt")]
+    #[case(0o1776, "rwxrwxrwT")]
+    fn symbolic_permissions_passes(#[case] mode: u32, #[case] expected: &str) {
+        assert_eq!(symbolic_permissions(mode), expected);
+    }
+
+    #[rstest]
+    #[case("", "")]
+    #[case("plain", "plain")]
+    #[case("a,b", "\"a,b\"")]
+    #[case("say \"hi\"", "\"say \"\"hi\"\"\"")]
+    #[case("line\nbreak", "\"line\nbreak\"")]
+    #[case("line\rbreak", "\"line\rbreak\"")]
+    fn csv_field_passes(#[case] field: &str, #[case] expected: &str) {
+        assert_eq!(csv_field(field), expected);
+    }
+
+    #[rstest]
+    #[case("", "''")]
+    #[case("file", "'file'")]
+    #[case("a b", "'a b'")]
+    #[case("$HOME/*", "'$HOME/*'")]
+    #[case("it's", "'it'\\''s'")]
+    #[case("''", "''\\'''\\'''")]
+    fn shell_quote_passes(#[case] s: &str, #[case] expected: &str) {
+        assert_eq!(shell_quote(s), expected);
+    }
+
+    #[rstest]
+    #[case("a/b", "x/y", "x/y", &["b", "a"])]
+    #[case("./a", "x", "x", &["a"])]
+    #[case("../c", "x/y", "x/y", &["c", ".."])]
+    #[case("/etc/passwd", "x/y", "", &["passwd", "etc"])]
+    fn push_link_target_passes(
+        #[case] target: &str,
+        #[case] dir: &str,
+        #[case] expected_dir: &str,
+        #[case] expected: &[&str],
+    ) {
+        let mut dir = PathBuf::from(dir);
+        let mut pending = vec![OsString::from("z")];
+        push_link_target(Path::new(target), &mut dir, &mut pending);
+        assert_eq!(dir, PathBuf::from(expected_dir));
+        // the names are resolved from the end and come before the names pending before
+        let expected: Vec<_> = once("z").chain(expected.iter().copied()).collect();
+        assert_eq!(pending, expected);
     }
 }
@@ -1,9 +1,16 @@
-use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    path::{self, Path, PathBuf},
+};
+
+use anyhow::{Result, bail};
 use crossterm::event::{Event, KeyCode, KeyEventKind};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use itertools::Either;
 use ratatui::prelude::*;
 use rustic_core::{
     IndexedFull, LocalDestination, LsOptions, ProgressBars, Repository, RestoreOptions,
-    RestorePlan, repofile::Node,
+    RestorePlan, RusticResult, repofile::Node,
 };
 
 use crate::{
@@ -14,11 +21,13 @@ use crate::{
     helpers::bytes_size_to_string,
 };
 
-use super::widgets::popup_text;
+use super::widgets::{popup_scrollable_text, popup_text};
 
 // the states this screen can be in
 enum CurrentScreen {
     GetDestination(PopUpInput),
+    GetPatterns(PopUpInput),
+    GetStripComponents(PopUpInput),
     PromptRestore(PopUpPrompt, Option<RestorePlan>),
     RestoreDone(PopUpText),
 }
@@ -27,23 +36,95 @@ pub(crate) struct Restore<'a, P, S> {
     current_screen: CurrentScreen,
     repo: &'a Repository<P, S>,
     opts: RestoreOptions,
-    node: Node,
+    nodes: Vec<Node>,
+    size: Option<u64>, // total size of all nodes, if known
     source: String,
     dest: String,
+    patterns: RestorePatterns,
+    skipped: usize, // number of entries skipped due to `patterns`
+    strip: usize,   // number of leading path components to strip from the restored paths
+    // the planned changes for display
+    plan_text: String,
+    // report of the dry run, shown on top of the restore prompt
+    dry_run: Option<PopUpInput>,
+}
+
+/// Number of paths shown as examples in the dry run report
+const DRY_RUN_SAMPLES: usize = 10;
+
+/// Glob patterns selecting the entries to restore
+///
+/// Patterns are matched against the paths relative to the restore root or against the file names.
+/// Patterns starting with `!` exclude entries, all others include them. If there are include patterns,
+/// only entries matching them are restored. Patterns also apply to all entries below a matching dir.
+#[derive(Clone, Default)]
+struct RestorePatterns {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl RestorePatterns {
+    /// Parse whitespace-separated glob patterns
+    fn parse(patterns: &str) -> Result<Self> {
+        let mut include = GlobSetBuilder::new();
+        let mut exclude = GlobSetBuilder::new();
+        let (mut has_include, mut has_exclude) = (false, false);
+        for pattern in patterns.split_whitespace() {
+            if let Some(pattern) = pattern.strip_prefix('!') {
+                _ = exclude.add(Glob::new(pattern)?);
+                has_exclude = true;
+            } else {
+                _ = include.add(Glob::new(pattern)?);
+                has_include = true;
+            }
+        }
+        Ok(Self {
+            include: has_include.then(|| include.build()).transpose()?,
+            exclude: has_exclude.then(|| exclude.build()).transpose()?,
+        })
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none()
+    }
+
+    /// Whether the entry with the given `path` is restored
+    fn matches(&self, path: &Path) -> bool {
+        let matches = |globset: &GlobSet| {
+            path.ancestors()
+                .filter(|path| !path.as_os_str().is_empty())
+                .any(|path| {
+                    globset.is_match(path) || path.file_name().is_some_and(|f| globset.is_match(f))
+                })
+        };
+        self.include.as_ref().is_none_or(matches) && !self.exclude.as_ref().is_some_and(matches)
+    }
 }
 
 impl<'a, P: ProgressBars, S: IndexedFull> Restore<'a, P, S> {
-    pub fn new(repo: &'a Repository<P, S>, node: Node, source: String, path: &str) -> Self {
+    pub fn new(
+        repo: &'a Repository<P, S>,
+        nodes: Vec<Node>,
+        size: Option<u64>,
+        source: String,
+        path: &str,
+    ) -> Self {
         let opts = RestoreOptions::default();
         let title = format!("restore {source} to:");
         let popup = popup_input(title, "enter restore destination", path, 1);
         Self {
             current_screen: CurrentScreen::GetDestination(popup),
-            node,
+            nodes,
+            size,
             repo,
             opts,
             source,
             dest: String::new(),
+            patterns: RestorePatterns::default(),
+            skipped: 0,
+            strip: 0,
+            plan_text: String::new(),
+            dry_run: None,
         }
     }
 
@@ -52,13 +133,16 @@ impl<'a, P: ProgressBars, S: IndexedFull> Restore<'a, P, S> {
             dest = ".".to_string();
         }
         self.dest = dest;
-        let dest = LocalDestination::new(&self.dest, true, !self.node.is_dir())?;
-
-        // for restore, always recurse into tree
-        let mut ls_opts = LsOptions::default();
-        ls_opts.recursive = true;
-
-        let ls = self.repo.ls(&self.node, &ls_opts)?;
+        let dest = LocalDestination::new(&self.dest, true, self.is_file())?;
+        let skipped = if self.patterns.is_empty() {
+            0
+        } else {
+            self.ls_all()?
+                .filter(|item| matches!(item, Ok((path, _)) if !self.patterns.matches(path)))
+                .count()
+        };
+        self.skipped = skipped;
+        let ls = self.ls()?;
 
         let plan = self.repo.prepare_restore(&self.opts, ls, &dest, dry_run)?;
 
@@ -70,13 +154,8 @@ impl<'a, P: ProgressBars, S: IndexedFull> Restore<'a, P, S> {
     // Note: This currently runs `prepare_restore` again and doesn't use `plan`
     // TODO: Fix when restore is changed such that `prepare_restore` is always dry_run and all modification is done in `restore`
     fn restore(&self, _plan: RestorePlan) -> Result<()> {
-        let dest = LocalDestination::new(&self.dest, true, !self.node.is_dir())?;
-
-        // for restore, always recurse into tree
-        let mut ls_opts = LsOptions::default();
-        ls_opts.recursive = true;
-
-        let ls = self.repo.ls(&self.node, &ls_opts)?;
+        let dest = LocalDestination::new(&self.dest, true, self.is_file())?;
+        let ls = self.ls()?;
         let plan = self
             .repo
             .prepare_restore(&self.opts, ls.clone(), &dest, false)?;
@@ -86,52 +165,296 @@ impl<'a, P: ProgressBars, S: IndexedFull> Restore<'a, P, S> {
         Ok(())
     }
 
+    /// The total size of all nodes to restore for display
+    fn size_text(&self) -> String {
+        self.size.map_or_else(
+            || {
+                let size = self.nodes.iter().map(|node| node.meta.size).sum();
+                format!(
+                    "at least {} (sizes of dirs are not computed)",
+                    bytes_size_to_string(size)
+                )
+            },
+            bytes_size_to_string,
+        )
+    }
+
+    /// Whether a single file is restored, i.e. the destination is a file
+    fn is_file(&self) -> bool {
+        matches!(self.nodes.as_slice(), [node] if !node.is_dir())
+    }
+
+    /// All nodes to restore with their paths relative to the destination, filtered by the patterns
+    ///
+    /// If leading path components are stripped, entries whose path is stripped completely are skipped.
+    /// Dirs which then have the same path are merged; an error is returned if other entries then
+    /// have the same path, as only one of them could be restored.
+    fn ls(&self) -> Result<impl Iterator<Item = RusticResult<(PathBuf, Node)>> + Clone> {
+        let patterns = self.patterns.clone();
+        let ls = self.ls_all()?.filter(move |item| match item {
+            Ok((path, _)) => patterns.matches(path),
+            Err(_) => true,
+        });
+        if self.strip == 0 {
+            return Ok(Either::Left(ls));
+        }
+        // stripping changes the order of the paths, but restoring needs them in order
+        let mut items = BTreeMap::new();
+        for item in ls {
+            let (path, node) = item?;
+            let path: PathBuf = path.components().skip(self.strip).collect();
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            if items
+                .get(&path)
+                .is_some_and(|other: &Node| !(node.is_dir() && other.is_dir()))
+            {
+                bail!(
+                    "stripping {} leading path components maps several entries to {}, please strip fewer components",
+                    self.strip,
+                    path.display()
+                );
+            }
+            _ = items.insert(path, node);
+        }
+        let items: Vec<_> = items.into_iter().collect();
+        Ok(Either::Right(items.into_iter().map(Ok)))
+    }
+
+    /// All nodes below the selected nodes with their paths relative to the destination
+    ///
+    /// Multiple nodes are restored below the destination using their names.
+    fn ls_all(&self) -> Result<impl Iterator<Item = RusticResult<(PathBuf, Node)>> + Clone> {
+        // for restore, always recurse into tree
+        let mut ls_opts = LsOptions::default();
+        ls_opts.recursive = true;
+
+        if let [node] = self.nodes.as_slice() {
+            return Ok(Either::Left(self.repo.ls(node, &ls_opts)?));
+        }
+        let mut items = Vec::new();
+        for node in &self.nodes {
+            let name = PathBuf::from(node.name());
+            items.push((name.clone(), node.clone()));
+            if node.is_dir() {
+                for item in self.repo.ls(node, &ls_opts)? {
+                    let (path, node) = item?;
+                    items.push((name.join(path), node));
+                }
+            }
+        }
+        Ok(Either::Right(items.into_iter().map(Ok)))
+    }
+
+    /// Compute the restore plan for `dest` and ask to confirm restoring
+    ///
+    /// This can also be used to skip asking for the destination.
+    pub fn restore_to(&mut self, dest: String) -> Result<()> {
+        let plan = self.compute_plan(dest, true)?;
+        let fs = plan.stats.files;
+        let ds = plan.stats.dirs;
+        self.plan_text = format!(
+            "Files:  {} to restore, {} unchanged, {} verified, {} to modify, {} additional
+Dirs:   {} to restore, {} to modify, {} additional
+Total restore size: {}",
+            fs.restore,
+            fs.unchanged,
+            fs.verified,
+            fs.modify,
+            fs.additional,
+            ds.restore,
+            ds.modify,
+            ds.additional,
+            bytes_size_to_string(plan.restore_size)
+        );
+        let target = path::absolute(&self.dest)
+            .map_or_else(|_| self.dest.clone(), |p| p.display().to_string());
+        let popup = popup_prompt(
+            "restore information",
+            Text::from(format!(
+                r#"
+restoring from: {}
+restoring to: {}
+items: {}, total size: {}{}{}
+                
+{}
+
+Do you want to proceed (y/n)? Press d to see what would be done first.
+ "#,
+                self.source,
+                target,
+                self.nodes.len(),
+                self.size_text(),
+                self.skipped_text(),
+                self.strip_text(),
+                self.plan_text,
+            )),
+        );
+        self.current_screen = CurrentScreen::PromptRestore(popup, Some(plan));
+        Ok(())
+    }
+
+    /// Report what restoring would do without writing anything: the planned changes and examples
+    /// of the paths which would be created or which already exist
+    fn dry_run_report(&self) -> Result<PopUpInput> {
+        let (mut created, mut existing) = ((0, Vec::new()), (0, Vec::new()));
+        for item in self.ls()? {
+            let (path, _) = item?;
+            let target = if self.is_file() {
+                PathBuf::from(&self.dest)
+            } else {
+                Path::new(&self.dest).join(path)
+            };
+            let (count, samples) = if target.symlink_metadata().is_ok() {
+                &mut existing
+            } else {
+                &mut created
+            };
+            *count += 1;
+            if samples.len() < DRY_RUN_SAMPLES {
+                samples.push(format!("  {}", target.display()));
+            }
+        }
+        let mut text = vec![
+            "Nothing has been written.".to_string(),
+            String::new(),
+            self.plan_text.clone(),
+        ];
+        if self.skipped > 0 {
+            text.push(format!(
+                "{} entries are skipped due to the patterns",
+                self.skipped
+            ));
+        }
+        for ((count, samples), what) in [
+            (created, "would be created"),
+            (
+                existing,
+                "already exist and would be checked and modified if needed",
+            ),
+        ] {
+            text.push(String::new());
+            text.push(format!(
+                "{count} entries {what}{}",
+                if count > 0 { ", e.g.:" } else { "" }
+            ));
+            text.extend(samples);
+        }
+        let lines = text.len();
+        Ok(popup_scrollable_text(
+            "DRY RUN",
+            &text.join("\n"),
+            (lines + 1).min(40).try_into().unwrap(),
+        ))
+    }
+
+    /// The number of skipped entries for display, if any
+    fn skipped_text(&self) -> String {
+        if self.skipped == 0 {
+            String::new()
+        } else {
+            format!(", skipped {} entries due to the patterns", self.skipped)
+        }
+    }
+
+    /// The number of stripped path components for display, if any
+    fn strip_text(&self) -> String {
+        if self.strip == 0 {
+            String::new()
+        } else {
+            format!(", stripping {} leading path components", self.strip)
+        }
+    }
+
     pub fn input(&mut self, event: Event) -> Result<bool> {
         use KeyCode::{Char, Enter, Esc};
+        if let Some(report) = &mut self.dry_run {
+            match report.input(event) {
+                TextInputResult::Cancel | TextInputResult::Input(_) => self.dry_run = None,
+                TextInputResult::None => {}
+            }
+            return Ok(false);
+        }
+        let dry_run_key = matches!(&event, Event::Key(key)
+            if key.kind == KeyEventKind::Press && key.code == Char('d'));
         match &mut self.current_screen {
             CurrentScreen::GetDestination(prompt) => match prompt.input(event) {
                 TextInputResult::Cancel => return Ok(true),
                 TextInputResult::Input(input) => {
-                    let plan = self.compute_plan(input, true)?;
-                    let fs = plan.stats.files;
-                    let ds = plan.stats.dirs;
-                    let popup = popup_prompt(
-                        "restore information",
-                        Text::from(format!(
-                            r#"
-restoring from: {}
-restoring to: {}
-                            
-Files:  {} to restore, {} unchanged, {} verified, {} to modify, {} additional
-Dirs:   {} to restore, {} to modify, {} additional
-Total restore size: {}
-
-Do you want to proceed (y/n)?
- "#,
-                            self.source,
-                            self.dest,
-                            fs.restore,
-                            fs.unchanged,
-                            fs.verified,
-                            fs.modify,
-                            fs.additional,
-                            ds.restore,
-                            ds.modify,
-                            ds.additional,
-                            bytes_size_to_string(plan.restore_size)
-                        )),
-                    );
-                    self.current_screen = CurrentScreen::PromptRestore(popup, Some(plan));
+                    self.dest = input;
+                    self.current_screen = CurrentScreen::GetPatterns(popup_input(
+                        "restore only entries matching (optional):",
+                        "enter glob patterns, use !pattern to exclude",
+                        "",
+                        1,
+                    ));
                 }
                 TextInputResult::None => {}
             },
+            CurrentScreen::GetPatterns(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => return Ok(true),
+                TextInputResult::Input(input) => match RestorePatterns::parse(&input) {
+                    Ok(patterns) => {
+                        self.patterns = patterns;
+                        if self.is_file() {
+                            self.restore_to(self.dest.clone())?;
+                        } else {
+                            self.current_screen = CurrentScreen::GetStripComponents(popup_input(
+                                "strip leading path components (optional):",
+                                "enter number of components",
+                                "0",
+                                1,
+                            ));
+                        }
+                    }
+                    Err(err) => {
+                        self.current_screen = CurrentScreen::RestoreDone(popup_text(
+                            "invalid patterns",
+                            format!("{err}").into(),
+                        ));
+                    }
+                },
+                TextInputResult::None => {}
+            },
+            CurrentScreen::GetStripComponents(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel => return Ok(true),
+                TextInputResult::Input(input) => match input.trim().parse() {
+                    Ok(strip) => {
+                        self.strip = strip;
+                        // e.g. if stripping maps several entries to the same path
+                        if let Err(err) = self.restore_to(self.dest.clone()) {
+                            self.current_screen = CurrentScreen::RestoreDone(popup_text(
+                                "cannot restore",
+                                format!("{err}").into(),
+                            ));
+                        }
+                    }
+                    Err(_) => {
+                        self.current_screen = CurrentScreen::RestoreDone(popup_text(
+                            "invalid number",
+                            format!("cannot strip {input:?} path components").into(),
+                        ));
+                    }
+                },
+                TextInputResult::None => {}
+            },
+            CurrentScreen::PromptRestore(..) if dry_run_key => {
+                self.dry_run = Some(self.dry_run_report()?);
+            }
             CurrentScreen::PromptRestore(prompt, plan) => match prompt.input(event) {
                 PromptResult::Ok => {
                     let plan = plan.take().unwrap();
                     self.restore(plan)?;
                     self.current_screen = CurrentScreen::RestoreDone(popup_text(
                         "restore done",
-                        format!("restored {} successfully to {}", self.source, self.dest).into(),
+                        format!(
+                            "restored {} successfully to {}{}",
+                            self.source,
+                            self.dest,
+                            self.skipped_text()
+                        )
+                        .into(),
                     ));
                 }
                 PromptResult::Cancel => return Ok(true),
@@ -152,9 +475,16 @@ Do you want to proceed (y/n)?
     pub fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
         // draw popups
         match &mut self.current_screen {
-            CurrentScreen::GetDestination(popup) => popup.draw(area, f),
+            CurrentScreen::GetDestination(popup)
+            | CurrentScreen::GetPatterns(popup)
+            | CurrentScreen::GetStripComponents(popup) => {
+                popup.draw(area, f);
+            }
             CurrentScreen::PromptRestore(popup, _) => popup.draw(area, f),
             CurrentScreen::RestoreDone(popup) => popup.draw(area, f),
         }
+        if let Some(report) = &mut self.dry_run {
+            report.draw(area, f);
+        }
     }
 }
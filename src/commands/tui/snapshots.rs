@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet, iter::once, mem, str::FromStr};
+use std::{collections::BTreeSet, fmt::Write, iter::once, mem, str::FromStr};
 
 use anyhow::Result;
 use chrono::Local;
@@ -9,40 +9,45 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
 };
 use rustic_core::{
-    IndexedFull, ProgressBars, Repository, SnapshotGroup, SnapshotGroupCriterion, StringList,
+    BlobType, FileType, IndexedFull, ProgressBars, Repository, SnapshotGroup,
+    SnapshotGroupCriterion, StringList,
     repofile::{DeleteOption, SnapshotFile},
 };
 use style::palette::tailwind;
 
 use crate::{
+    Application, RUSTIC_APP,
     commands::{
         snapshots::{fill_table, snap_to_table},
         tui::{
             diff::{Diff, DiffResult},
-            ls::{Snapshot, SnapshotResult},
+            ls::SnapshotResult,
+            tabs::SnapshotTabs,
             tree::{Tree, TreeIterItem, TreeNode},
             widgets::{
                 Draw, PopUpInput, PopUpPrompt, PopUpTable, PopUpText, ProcessEvent, PromptResult,
-                SelectTable, TextInputResult, WithBlock, popup_input, popup_prompt, popup_table,
-                popup_text,
+                SelectTable, TextInputResult, WithBlock, popup_input, popup_prompt,
+                popup_scrollable_text, popup_table, popup_text,
             },
         },
     },
     filtering::SnapshotFilter,
+    helpers::{DisplayTimeZone, bytes_size_to_string},
 };
 
-use super::summary::SummaryMap;
+use super::{Background, Job, summary::SummaryMap};
 
 // the states this screen can be in
 enum CurrentScreen<'a, P, S> {
     Snapshots,
     ShowHelp(PopUpText),
     SnapshotDetails(PopUpTable),
+    RepoInfo(PopUpInput),
     EnterProperty((PopUpInput, SnapshotProperty)),
     EnterFilter(PopUpInput),
     PromptWrite(PopUpPrompt),
     PromptExit(PopUpPrompt),
-    Dir(Box<Snapshot<'a, P, S>>),
+    Dir(Box<SnapshotTabs<'a, P, S>>),
     Diff(Box<Diff<'a, P, S>>),
 }
 
@@ -126,11 +131,12 @@ const INFO_TEXT: &str = "(Esc) quit | (F5) reload snapshots | (Enter) show conte
 const HELP_TEXT: &str = r"General Commands:
   q, Esc : exit
       F5 : re-read all snapshots from repository
-   Enter : show snapshot contents
+   Enter : show snapshot contents (of marked snapshots in tabs)
        v : toggle snapshot view [Filtered -> All -> Marked -> Modified]
        V : modify filter to use     
   Ctrl-v : reset filter
        i : show detailed snapshot information for selected snapshot
+       I : show summary of the whole repository
        w : write modified snapshots and delete snapshots to-forget
        ? : show this help page
  
@@ -172,9 +178,10 @@ pub struct Snapshots<'a, P, S> {
     default_filter: SnapshotFilter,
     group_by: SnapshotGroupCriterion,
     summary_map: SummaryMap,
+    time_zone: DisplayTimeZone,
 }
 
-impl<'a, P: ProgressBars, S: IndexedFull> Snapshots<'a, P, S> {
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> Snapshots<'a, P, S> {
     pub fn new(
         repo: &'a Repository<P, S>,
         filter: SnapshotFilter,
@@ -190,7 +197,10 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshots<'a, P, S> {
         let mut app = Self {
             current_screen: CurrentScreen::Snapshots,
             current_view: View::Filter,
-            table: WithBlock::new(SelectTable::new(header), Block::new()),
+            table: WithBlock::new(
+                SelectTable::new(header, RUSTIC_APP.config().tui.wrap_around()),
+                Block::new(),
+            ),
             repo,
             snaps_status: Vec::new(),
             original_snapshots: Vec::new(),
@@ -201,6 +211,7 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshots<'a, P, S> {
             filter,
             group_by,
             summary_map: SummaryMap::default(),
+            time_zone: RUSTIC_APP.config().global.time_zone(),
         };
         app.reread()?;
         Ok(app)
@@ -389,7 +400,7 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshots<'a, P, S> {
                 };
                 let count = info.tree.child_count();
                 once(&mark.to_string())
-                    .chain(snap_to_table(snap, count).iter())
+                    .chain(snap_to_table(snap, count, self.time_zone).iter())
                     .cloned()
                     .enumerate()
                     .map(|(i, mut content)| {
@@ -522,22 +533,107 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshots<'a, P, S> {
     pub fn snapshot_details(&self) -> PopUpTable {
         let mut rows = Vec::new();
         if let Some(snap) = self.selected_snapshot() {
-            fill_table(snap, |title, value| {
+            fill_table(snap, self.time_zone, |title, value| {
                 rows.push(vec![Text::from(title.to_string()), Text::from(value)]);
             });
         }
         popup_table("snapshot details", rows)
     }
 
-    pub fn dir(&mut self) -> Result<Option<Snapshot<'a, P, S>>> {
-        self.selected_snapshot().cloned().map_or(Ok(None), |snap| {
-            Some(Snapshot::new(
-                self.repo,
-                snap,
-                mem::take(&mut self.summary_map),
-            ))
-            .transpose()
-        })
+    pub fn repo_info(&self) -> Result<PopUpInput> {
+        let mut text = String::new();
+
+        let total_size: u64 = self
+            .original_snapshots
+            .iter()
+            .filter_map(|snap| snap.summary.as_ref())
+            .map(|summary| summary.total_bytes_processed)
+            .sum();
+        _ = writeln!(text, "repository: {}", self.repo.name);
+        _ = writeln!(text, "snapshots:  {}", self.original_snapshots.len());
+        _ = writeln!(text, "total size: {}", bytes_size_to_string(total_size));
+
+        let files = self.repo.infos_files()?;
+        _ = writeln!(text, "\nrepository files:");
+        for info in files.repo.iter().chain(files.repo_hot.iter().flatten()) {
+            _ = writeln!(
+                text,
+                "  {:<10} {:>8} files, {:>10}",
+                format!("{:?}", info.tpe),
+                info.count,
+                bytes_size_to_string(info.size)
+            );
+        }
+        let index_size: u64 = files
+            .repo
+            .iter()
+            .filter(|info| matches!(info.tpe, FileType::Index))
+            .map(|info| info.size)
+            .sum();
+        _ = writeln!(text, "index size: {}", bytes_size_to_string(index_size));
+
+        let index = self.repo.infos_index()?;
+        _ = writeln!(text, "\nblobs:");
+        for blobs in &index.blobs {
+            _ = writeln!(
+                text,
+                "  {:<10} {:>8} blobs, {:>10} ({} in packs)",
+                format!("{:?}", blobs.blob_type),
+                blobs.count,
+                bytes_size_to_string(blobs.data_size),
+                bytes_size_to_string(blobs.size)
+            );
+        }
+        _ = writeln!(text, "\npacks:");
+        for packs in &index.packs {
+            _ = writeln!(
+                text,
+                "  {:<10} {:>8} packs",
+                format!("{:?}", packs.blob_type),
+                packs.count
+            );
+        }
+
+        let data_size: u64 = index
+            .blobs
+            .iter()
+            .filter(|blobs| matches!(blobs.blob_type, BlobType::Data))
+            .map(|blobs| blobs.data_size)
+            .sum();
+        if data_size > 0 {
+            #[allow(clippy::cast_precision_loss)]
+            let ratio = total_size as f64 / data_size as f64;
+            _ = writeln!(text, "\ndeduplication ratio: {ratio:.2}");
+        }
+
+        let lines = text.lines().count();
+        Ok(popup_scrollable_text(
+            "repository summary",
+            &text,
+            (lines + 1).min(40).try_into().unwrap(),
+        ))
+    }
+
+    /// Browse the marked snapshots in tabs (or the selected one if none is marked)
+    pub fn dir(&mut self) -> Result<Option<SnapshotTabs<'a, P, S>>> {
+        let snaps: Vec<_> = if self.has_mark() {
+            self.snapshots
+                .iter()
+                .zip(self.snaps_status.iter())
+                .filter_map(|(snap, status)| status.marked.then(|| snap.clone()))
+                .collect()
+        } else {
+            self.selected_snapshot().cloned().into_iter().collect()
+        };
+        if snaps.is_empty() {
+            return Ok(None);
+        }
+        Some(SnapshotTabs::new(
+            self.repo,
+            snaps,
+            mem::take(&mut self.summary_map),
+        ))
+        .transpose()
     }
 
     pub fn diff(&mut self) -> Result<Option<Diff<'a, P, S>>> {
@@ -783,7 +879,7 @@ impl<'a, P: ProgressBars, S: IndexedFull> Snapshots<'a, P, S> {
     }
 }
 
-impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Snapshots<'a, P, S> {
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> ProcessEvent for Snapshots<'a, P, S> {
     type Result = Result<bool>;
     fn input(&mut self, event: Event) -> Result<bool> {
         use KeyCode::{Char, Enter, Esc, F, Left, Right};
@@ -854,6 +950,10 @@ impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Snapshots<'a, P, S> {
                                     self.current_screen =
                                         CurrentScreen::SnapshotDetails(self.snapshot_details());
                                 }
+                                Char('I') => {
+                                    self.current_screen =
+                                        CurrentScreen::RepoInfo(self.repo_info()?);
+                                }
                                 Char('l') => {
                                     self.current_screen = CurrentScreen::EnterProperty((
                                         popup_input(
@@ -938,6 +1038,12 @@ impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Snapshots<'a, P, S> {
                 }
                 _ => {}
             },
+            CurrentScreen::RepoInfo(prompt) => match prompt.input(event) {
+                TextInputResult::Cancel | TextInputResult::Input(_) => {
+                    self.current_screen = CurrentScreen::Snapshots;
+                }
+                TextInputResult::None => {}
+            },
             CurrentScreen::EnterProperty((prompt, _)) | CurrentScreen::EnterFilter(prompt) => {
                 match prompt.input(event) {
                     TextInputResult::Cancel => self.current_screen = CurrentScreen::Snapshots,
@@ -982,7 +1088,25 @@ impl<'a, P: ProgressBars, S: IndexedFull> ProcessEvent for Snapshots<'a, P, S> {
     }
 }
 
-impl<'a, P: ProgressBars, S: IndexedFull> Draw for Snapshots<'a, P, S> {
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> Background<'a> for Snapshots<'a, P, S> {
+    fn take_job(&mut self) -> Option<Job<'a>> {
+        match &mut self.current_screen {
+            CurrentScreen::Dir(dir) => dir.take_job(),
+            CurrentScreen::Diff(diff) => diff.take_job(),
+            _ => None,
+        }
+    }
+
+    fn update(&mut self) -> bool {
+        match &mut self.current_screen {
+            CurrentScreen::Dir(dir) => dir.update(),
+            CurrentScreen::Diff(diff) => diff.update(),
+            _ => false,
+        }
+    }
+}
+
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> Draw for Snapshots<'a, P, S> {
     fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
         if let CurrentScreen::Dir(dir) = &mut self.current_screen {
             dir.draw(area, f);
@@ -1010,6 +1134,7 @@ impl<'a, P: ProgressBars, S: IndexedFull> Draw for Snapshots<'a, P, S> {
         // draw popups
         match &mut self.current_screen {
             CurrentScreen::SnapshotDetails(popup) => popup.draw(area, f),
+            CurrentScreen::RepoInfo(popup) => popup.draw(area, f),
             CurrentScreen::ShowHelp(popup) => popup.draw(area, f),
             CurrentScreen::EnterProperty((popup, _)) | CurrentScreen::EnterFilter(popup) => {
                 popup.draw(area, f);
@@ -1,32 +1,338 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, btree_map::Entry},
+    num::NonZero,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use rustic_core::{
-    DataId, IndexedFull, Progress, Repository, TreeId,
+    DataId, IndexedFull, NoProgress, Progress, Repository, TreeId,
     repofile::{Metadata, Node, Tree},
 };
 
-use crate::{commands::ls::Summary, helpers::bytes_size_to_string};
+use crate::{
+    commands::{ls::Summary, tui::Job},
+    helpers::bytes_size_to_string,
+};
+
+/// Maximum number of threads used to compute summaries
+const MAX_THREADS: usize = 8;
 
-#[derive(Default)]
+/// How long idle threads wait for new trees before checking for cancellation again
+const WAIT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Default, Clone)]
 pub struct SummaryMap(BTreeMap<TreeId, TreeSummary>);
 
+/// The state shared by the threads computing summaries
+#[derive(Default)]
+struct Jobs {
+    summaries: BTreeMap<TreeId, TreeSummary>,
+    /// trees to fetch; taken last-in-first-out so that subtrees are completed before new ones are started
+    queue: Vec<TreeId>,
+    /// fetched trees which still wait for the given number of subtree summaries
+    pending: BTreeMap<TreeId, (Tree, usize)>,
+    /// the trees waiting for the summary of a queued, fetched or pending tree
+    parents: BTreeMap<TreeId, Vec<TreeId>>,
+    /// number of trees currently being processed
+    active: usize,
+}
+
 impl SummaryMap {
     pub fn get(&self, id: &TreeId) -> Option<&TreeSummary> {
         self.0.get(id)
     }
 
-    pub fn compute<P, S: IndexedFull>(
+    /// Add all summaries of `other`
+    pub fn merge(&mut self, other: Self) {
+        self.0.extend(other.0);
+    }
+
+    /// The trees which occur more than once below the tree `root`, i.e. dirs with identical contents
+    ///
+    /// Only trees with a computed summary are taken into account. Trees below a shared tree are not
+    /// included, unless they also occur elsewhere.
+    pub fn shared_trees(&self, root: TreeId) -> BTreeSet<TreeId> {
+        let mut visited = BTreeSet::new();
+        let mut shared = BTreeSet::new();
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id) {
+                _ = shared.insert(id);
+                continue;
+            }
+            if let Some(summary) = self.0.get(&id) {
+                stack.extend(&summary.subtrees);
+            }
+        }
+        shared
+    }
+
+    /// Compute the summaries of the tree `id` and all its subtrees
+    ///
+    /// The trees are fetched and summarized by multiple threads, each tree is dropped as soon as its
+    /// summary is stored. `report` is called with each new summary. Returns `false` if the
+    /// computation was cancelled by setting `cancel`; the summaries of all subtrees completed so far
+    /// are kept.
+    pub fn compute<P: Sync, S: IndexedFull + Sync>(
         &mut self,
         repo: &Repository<P, S>,
         id: TreeId,
         p: &impl Progress,
+        cancel: &AtomicBool,
+        report: impl Fn(TreeId, &TreeSummary) + Sync,
+    ) -> Result<bool> {
+        let threads = thread::available_parallelism()
+            .map_or(1, NonZero::get)
+            .min(MAX_THREADS);
+        self.compute_with(id, |id| Ok(repo.get_tree(id)?), threads, p, cancel, report)
+    }
+
+    /// Compute the summaries of the tree `id` and all its subtrees using `threads` threads and
+    /// `get_tree` to fetch the trees
+    fn compute_with(
+        &mut self,
+        id: TreeId,
+        get_tree: impl Fn(&TreeId) -> Result<Tree> + Sync,
+        threads: usize,
+        p: &impl Progress,
+        cancel: &AtomicBool,
+        report: impl Fn(TreeId, &TreeSummary) + Sync,
+    ) -> Result<bool> {
+        if self.0.contains_key(&id) {
+            return Ok(true);
+        }
+        let jobs = Mutex::new(Jobs {
+            summaries: std::mem::take(&mut self.0),
+            queue: vec![id],
+            ..Default::default()
+        });
+        let changed = Condvar::new();
+        let failed = AtomicBool::new(false);
+        let result = thread::scope(|s| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    s.spawn(|| {
+                        let result =
+                            Jobs::work(&jobs, &changed, &get_tree, p, cancel, &failed, &report);
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        result
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect::<Result<Vec<_>>>()
+        });
+        self.0 = jobs.into_inner().unwrap().summaries;
+        _ = result?;
+        Ok(self.0.contains_key(&id))
+    }
+}
+
+impl Jobs {
+    /// Process trees from the queue until all trees are summarized, the computation is cancelled
+    /// or another thread failed
+    fn work(
+        jobs: &Mutex<Self>,
+        changed: &Condvar,
+        get_tree: &impl Fn(&TreeId) -> Result<Tree>,
+        p: &impl Progress,
+        cancel: &AtomicBool,
+        failed: &AtomicBool,
+        report: &impl Fn(TreeId, &TreeSummary),
     ) -> Result<()> {
-        let _ = TreeSummary::from_tree(repo, id, &mut self.0, p)?;
+        loop {
+            let id = {
+                let mut state = jobs.lock().unwrap();
+                loop {
+                    if cancel.load(Ordering::Relaxed) || failed.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    if let Some(id) = state.queue.pop() {
+                        state.active += 1;
+                        break id;
+                    }
+                    if state.active == 0 {
+                        // no trees queued or in process, so all summaries are complete
+                        return Ok(());
+                    }
+                    state = changed.wait_timeout(state, WAIT_INTERVAL).unwrap().0;
+                }
+            };
+            let result = Self::process(jobs, id, get_tree, p, report);
+            jobs.lock().unwrap().active -= 1;
+            changed.notify_all();
+            result?;
+        }
+    }
+
+    /// Fetch the tree `id` and queue its subtrees without summary; summarize it (and all trees which
+    /// only waited for it) if there are none
+    fn process(
+        jobs: &Mutex<Self>,
+        id: TreeId,
+        get_tree: &impl Fn(&TreeId) -> Result<Tree>,
+        p: &impl Progress,
+        report: &impl Fn(TreeId, &TreeSummary),
+    ) -> Result<()> {
+        let tree = get_tree(&id)?;
+        p.inc(1);
+
+        let mut state = jobs.lock().unwrap();
+        let missing: BTreeSet<_> = tree
+            .nodes
+            .iter()
+            .filter_map(|node| node.subtree)
+            .filter(|id| !state.summaries.contains_key(id))
+            .collect();
+        if !missing.is_empty() {
+            for subtree in &missing {
+                match state.parents.entry(*subtree) {
+                    Entry::Occupied(parents) => parents.into_mut().push(id),
+                    Entry::Vacant(parents) => {
+                        _ = parents.insert(vec![id]);
+                        state.queue.push(*subtree);
+                    }
+                }
+            }
+            _ = state.pending.insert(id, (tree, missing.len()));
+            return Ok(());
+        }
+
+        let mut complete = vec![(id, tree)];
+        while let Some((id, tree)) = complete.pop() {
+            let subtrees: BTreeMap<_, _> = tree
+                .nodes
+                .iter()
+                .filter_map(|node| node.subtree)
+                .map(|id| {
+                    let summary = &state.summaries[&id];
+                    (id, (summary.summary, summary.id_without_meta))
+                })
+                .collect();
+            // summarizing doesn't need the shared state, so let the other threads continue meanwhile
+            drop(state);
+            let summary = TreeSummary::from_tree(&tree, &subtrees)?;
+            drop(tree);
+            report(id, &summary);
+
+            state = jobs.lock().unwrap();
+            _ = state.summaries.insert(id, summary);
+            for parent in state.parents.remove(&id).unwrap_or_default() {
+                let Some((_, missing)) = state.pending.get_mut(&parent) else {
+                    continue;
+                };
+                *missing -= 1;
+                if *missing == 0 {
+                    let (tree, _) = state.pending.remove(&parent).unwrap();
+                    complete.push((parent, tree));
+                }
+            }
+        }
         Ok(())
     }
 }
 
+/// A message of the computation of summaries running in the background
+enum Message {
+    Summary(TreeId, TreeSummary),
+    Finished(Result<bool>),
+}
+
+/// The computation of summaries running in the background, which is cancelled when dropped
+pub struct SummaryJob {
+    messages: Receiver<Message>,
+    cancel: Arc<AtomicBool>,
+    started: Instant,
+    computed: usize,
+}
+
+impl SummaryJob {
+    /// Prepare computing the summaries of the trees `ids` and all their subtrees, reusing the
+    /// summaries of `summary_map`
+    ///
+    /// The returned job has to be run in the background, its results are merged by [`Self::update`].
+    pub fn new<'a, P: Sync, S: IndexedFull + Sync>(
+        repo: &'a Repository<P, S>,
+        ids: Vec<TreeId>,
+        summary_map: &SummaryMap,
+    ) -> (Self, Job<'a>) {
+        let (sender, messages) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let token = cancel.clone();
+        let mut summaries = summary_map.clone();
+        let job: Job<'a> = Box::new(move || {
+            let report = |id, summary: &TreeSummary| {
+                _ = sender.send(Message::Summary(id, summary.clone()));
+            };
+            let result = ids.into_iter().try_fold(true, |complete, id| {
+                Ok(summaries.compute(repo, id, &NoProgress, &token, report)? && complete)
+            });
+            _ = sender.send(Message::Finished(result));
+        });
+        let summary_job = Self {
+            messages,
+            cancel,
+            started: Instant::now(),
+            computed: 0,
+        };
+        (summary_job, job)
+    }
+
+    /// Merge the summaries computed so far into `summary_map`
+    ///
+    /// Returns whether summaries were merged and, once the computation is finished, whether all
+    /// summaries were computed or the computation was cancelled.
+    pub fn update(&mut self, summary_map: &mut SummaryMap) -> (bool, Option<Result<bool>>) {
+        let mut merged = false;
+        loop {
+            match self.messages.try_recv() {
+                Ok(Message::Summary(id, summary)) => {
+                    _ = summary_map.0.insert(id, summary);
+                    self.computed += 1;
+                    merged = true;
+                }
+                Ok(Message::Finished(result)) => return (merged, Some(result)),
+                Err(TryRecvError::Empty) => return (merged, None),
+                Err(TryRecvError::Disconnected) => {
+                    return (merged, Some(Err(anyhow!("computing summaries stopped"))));
+                }
+            }
+        }
+    }
+
+    /// Cancel the computation, keeping the summaries computed so far
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// A status line with a spinner showing the progress
+    pub fn status(&self) -> String {
+        const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+        let frame = SPINNER[(self.started.elapsed().as_millis() / 100) as usize % SPINNER.len()];
+        format!(
+            "{frame} computing (sub)-dir information: {} dirs done (Esc to cancel)",
+            self.computed
+        )
+    }
+}
+
+impl Drop for SummaryJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct TreeSummary {
     pub id_without_meta: TreeId,
@@ -36,10 +342,6 @@ pub struct TreeSummary {
 }
 
 impl TreeSummary {
-    fn update(&mut self, other: Self) {
-        self.summary += other.summary;
-    }
-
     fn update_from_node(&mut self, node: &Node) {
         for id in node.content.iter().flatten() {
             _ = self.blobs.0.insert(*id);
@@ -47,24 +349,11 @@ impl TreeSummary {
         self.summary.update(node);
     }
 
-    pub fn from_tree<P, S>(
-        repo: &'_ Repository<P, S>,
-        id: TreeId,
-        summary_map: &mut BTreeMap<TreeId, Self>,
-        p: &impl Progress,
-    ) -> Result<Self>
-    where
-        S: IndexedFull,
-    {
-        if let Some(summary) = summary_map.get(&id) {
-            return Ok(summary.clone());
-        }
-
+    /// Compute the summary of `tree` from the summaries and the ids without metadata of all its
+    /// subtrees
+    fn from_tree(tree: &Tree, subtrees: &BTreeMap<TreeId, (Summary, TreeId)>) -> Result<Self> {
         let mut summary = Self::default();
-
-        let tree = repo.get_tree(&id)?;
         let mut tree_without_meta = Tree::default();
-        p.inc(1);
         for node in &tree.nodes {
             let mut node_without_meta = Node::new_node(
                 node.name().as_os_str(),
@@ -74,21 +363,67 @@ impl TreeSummary {
             node_without_meta.content = node.content.clone();
             summary.update_from_node(node);
             if let Some(id) = node.subtree {
-                let subtree_summary = Self::from_tree(repo, id, summary_map, p)?;
-                node_without_meta.subtree = Some(subtree_summary.id_without_meta);
-                summary.update(subtree_summary);
+                let (subtree_summary, id_without_meta) = subtrees[&id];
+                node_without_meta.subtree = Some(id_without_meta);
+                summary.summary += subtree_summary;
                 summary.subtrees.push(id);
             }
             tree_without_meta.nodes.push(node_without_meta);
         }
         let (_, id_without_meta) = tree_without_meta.serialize()?;
         summary.id_without_meta = id_without_meta;
-
-        _ = summary_map.insert(id, summary.clone());
         Ok(summary)
     }
 }
 
+/// Stored sizes of dirs and files, as computing them needs an index lookup for each blob
+#[derive(Default)]
+pub struct StoredSizeCache {
+    dirs: BTreeMap<TreeId, u64>,
+    files: BTreeMap<Vec<DataId>, u64>, // keyed by the contents of the file
+}
+
+impl StoredSizeCache {
+    /// Get the stored size of a node, computing it if not yet cached
+    ///
+    /// The size of a dir is only known once its summary is computed, so it is only cached then.
+    pub fn get<P, S: IndexedFull>(
+        &mut self,
+        repo: &Repository<P, S>,
+        node: &Node,
+        summary_map: &SummaryMap,
+    ) -> u64 {
+        if let Some(size) = self.cached(node) {
+            return size;
+        }
+        let size = BlobInfoRef::from_node_or_map(node, summary_map)
+            .stored_size(repo)
+            .unwrap_or_default();
+        match node.subtree {
+            Some(id) if summary_map.get(&id).is_some() => _ = self.dirs.insert(id, size),
+            Some(_) => {}
+            None => {
+                _ = self
+                    .files
+                    .insert(node.content.clone().unwrap_or_default(), size)
+            }
+        }
+        size
+    }
+
+    /// Get the stored size of a node if it is already cached
+    #[must_use]
+    pub fn cached(&self, node: &Node) -> Option<u64> {
+        match node.subtree {
+            Some(id) => self.dirs.get(&id).copied(),
+            None => self
+                .files
+                .get(node.content.as_deref().unwrap_or_default())
+                .copied(),
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct BlobInfo(BTreeSet<DataId>);
 
@@ -121,6 +456,13 @@ impl<'a> BlobInfoRef<'a> {
         Self(node.content.iter().flatten().collect())
     }
 
+    /// The total size of all blobs as stored in the repository, i.e. compressed and encrypted
+    pub fn stored_size<P, S: IndexedFull>(&self, repo: &Repository<P, S>) -> Result<u64> {
+        self.0.iter().try_fold(0, |sum, id| -> Result<_> {
+            Ok(sum + u64::from(repo.get_index_entry(*id)?.length))
+        })
+    }
+
     pub fn text_diff<P, S: IndexedFull>(
         blobs1: &Option<Self>,
         blobs2: &Option<Self>,
@@ -141,3 +483,185 @@ impl<'a> BlobInfoRef<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        ffi::OsStr,
+        sync::{Arc, atomic::AtomicU64},
+    };
+
+    use rstest::rstest;
+    use rustic_core::{NoProgress, repofile::NodeType};
+
+    /// Progress counting the increments
+    #[derive(Clone, Default)]
+    struct Counter(Arc<AtomicU64>);
+
+    impl Progress for Counter {
+        fn is_hidden(&self) -> bool {
+            true
+        }
+        fn set_length(&self, _len: u64) {}
+        fn set_title(&self, _title: &'static str) {}
+        fn inc(&self, inc: u64) {
+            _ = self.0.fetch_add(inc, Ordering::Relaxed);
+        }
+        fn finish(&self) {}
+    }
+
+    /// A tree containing a file of the given size and dirs with the given subtrees
+    fn tree(name: &str, size: u64, subtrees: &[TreeId]) -> Tree {
+        let meta = Metadata {
+            size,
+            ..Default::default()
+        };
+        let mut tree = Tree::default();
+        tree.nodes
+            .push(Node::new_node(OsStr::new(name), NodeType::File, meta));
+        for (i, id) in subtrees.iter().enumerate() {
+            let name = format!("dir{i}");
+            let mut dir = Node::new_node(OsStr::new(&name), NodeType::Dir, Metadata::default());
+            dir.subtree = Some(*id);
+            tree.nodes.push(dir);
+        }
+        tree
+    }
+
+    /// 10 dirs with 5 subdirs each and a subdir shared by all dirs; returns the root and all 62 trees
+    fn example_trees() -> (TreeId, BTreeMap<TreeId, Tree>) {
+        let mut trees = BTreeMap::new();
+        let mut add = |tree: Tree| {
+            let (_, id) = tree.serialize().unwrap();
+            _ = trees.insert(id, tree);
+            id
+        };
+        let shared = add(tree("shared", 1000, &[]));
+        let mut dirs = Vec::new();
+        for i in 0..10 {
+            let mut subdirs: Vec<_> = (0..5)
+                .map(|j| add(tree(&format!("file{j}"), i * 10 + j, &[])))
+                .collect();
+            subdirs.push(shared);
+            dirs.push(add(tree("file", i, &subdirs)));
+        }
+        dirs.push(shared);
+        let root = add(tree("root", 5, &dirs));
+        (root, trees)
+    }
+
+    /// All computed summaries in a comparable form
+    fn contents(summaries: &SummaryMap) -> Vec<(TreeId, usize, usize, u64, TreeId, Vec<TreeId>)> {
+        summaries
+            .0
+            .iter()
+            .map(|(id, sum)| {
+                let Summary { files, size, dirs } = sum.summary;
+                (
+                    *id,
+                    files,
+                    dirs,
+                    size,
+                    sum.id_without_meta,
+                    sum.subtrees.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compute_passes() {
+        let (root, trees) = example_trees();
+        let mut summaries = SummaryMap::default();
+        let cancel = AtomicBool::new(false);
+        let get_tree = |id: &TreeId| Ok(trees[id].clone());
+        let reported = Mutex::new(Vec::new());
+        let report = |id, _: &TreeSummary| reported.lock().unwrap().push(id);
+        assert!(
+            summaries
+                .compute_with(root, get_tree, 4, &NoProgress, &cancel, report)
+                .unwrap()
+        );
+        // each summary is reported once, the root last
+        let mut reported = reported.into_inner().unwrap();
+        assert_eq!(reported.last(), Some(&root));
+        reported.sort();
+        assert!(summaries.0.keys().eq(&reported));
+        let summary = summaries.get(&root).unwrap().summary;
+        assert_eq!(summary.files, 72);
+        assert_eq!(summary.dirs, 71);
+        assert_eq!(summary.size, 13_400);
+        assert_eq!(summaries.0.len(), trees.len());
+    }
+
+    #[rstest]
+    fn compute_is_deterministic(#[values(1, 2, 8)] threads: usize) {
+        let (root, trees) = example_trees();
+        let cancel = AtomicBool::new(false);
+        let get_tree = |id: &TreeId| Ok(trees[id].clone());
+        let mut expected = SummaryMap::default();
+        _ = expected
+            .compute_with(root, get_tree, 1, &NoProgress, &cancel, |_, _| {})
+            .unwrap();
+
+        for _ in 0..10 {
+            let mut summaries = SummaryMap::default();
+            let p = Counter::default();
+            assert!(
+                summaries
+                    .compute_with(root, get_tree, threads, &p, &cancel, |_, _| {})
+                    .unwrap()
+            );
+            assert_eq!(contents(&summaries), contents(&expected));
+            // each tree is fetched exactly once, even if it occurs multiple times
+            assert_eq!(p.0.load(Ordering::Relaxed), 62);
+        }
+
+        // reusing the summaries of a subtree computed before gives the same result
+        let mut summaries = SummaryMap::default();
+        let dir = trees[&root].nodes[1].subtree.unwrap();
+        _ = summaries
+            .compute_with(dir, get_tree, threads, &NoProgress, &cancel, |_, _| {})
+            .unwrap();
+        _ = summaries
+            .compute_with(root, get_tree, threads, &NoProgress, &cancel, |_, _| {})
+            .unwrap();
+        assert_eq!(contents(&summaries), contents(&expected));
+    }
+
+    #[test]
+    fn compute_cancelled_returns_false() {
+        let (root, trees) = example_trees();
+        let mut summaries = SummaryMap::default();
+        let cancel = AtomicBool::new(true);
+        let get_tree = |id: &TreeId| Ok(trees[id].clone());
+        assert!(
+            !summaries
+                .compute_with(root, get_tree, 4, &NoProgress, &cancel, |_, _| {})
+                .unwrap()
+        );
+        assert!(summaries.get(&root).is_none());
+    }
+
+    #[test]
+    fn compute_fails_if_tree_is_missing() {
+        let (root, mut trees) = example_trees();
+        let dir = trees[&root].nodes[1].subtree.unwrap();
+        _ = trees.remove(&dir);
+        let mut summaries = SummaryMap::default();
+        let cancel = AtomicBool::new(false);
+        let get_tree = |id: &TreeId| {
+            trees
+                .get(id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("tree {id:?} not found"))
+        };
+        assert!(
+            summaries
+                .compute_with(root, get_tree, 4, &NoProgress, &cancel, |_, _| {})
+                .is_err()
+        );
+    }
+}
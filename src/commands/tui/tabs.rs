@@ -0,0 +1,139 @@
+use anyhow::Result;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::{prelude::*, widgets::Tabs};
+use rustic_core::{IndexedFull, ProgressBars, Repository, repofile::SnapshotFile};
+use style::palette::tailwind;
+
+use crate::{
+    Application, RUSTIC_APP,
+    commands::tui::{
+        Job,
+        ls::{Snapshot, SnapshotResult},
+        summary::SummaryMap,
+    },
+};
+
+/// Several snapshots browsed in tabs, each with its own navigation state
+pub struct SnapshotTabs<'a, P, S> {
+    tabs: Vec<Snapshot<'a, P, S>>,
+    titles: Vec<String>,
+    active: usize,
+    // summaries of closed tabs
+    summary_map: SummaryMap,
+}
+
+impl<'a, P: ProgressBars + Sync, S: IndexedFull + Sync> SnapshotTabs<'a, P, S> {
+    /// Open a tab for each of the `snapshots`
+    ///
+    /// The first tab uses the already computed `summary_map`, the others start with empty summaries.
+    pub fn new(
+        repo: &'a Repository<P, S>,
+        snapshots: Vec<SnapshotFile>,
+        summary_map: SummaryMap,
+    ) -> Result<Self> {
+        let mut summary_map = Some(summary_map);
+        let zone = RUSTIC_APP.config().global.time_zone();
+        let titles = snapshots
+            .iter()
+            .map(|snap| format!("{} {}", snap.id, zone.format(&snap.time, "%Y-%m-%d %H:%M")))
+            .collect();
+        let tabs = snapshots
+            .into_iter()
+            .map(|snap| Snapshot::new(repo, snap, summary_map.take().unwrap_or_default()))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            tabs,
+            titles,
+            active: 0,
+            summary_map: SummaryMap::default(),
+        })
+    }
+
+    fn select_next(&mut self) {
+        self.active = (self.active + 1) % self.tabs.len();
+    }
+
+    fn select_previous(&mut self) {
+        self.active = (self.active + self.tabs.len() - 1) % self.tabs.len();
+    }
+
+    /// Close the active tab and switch to the next one
+    ///
+    /// Returns the collected summaries if this was the last tab.
+    fn close(&mut self, summary_map: SummaryMap) -> Option<SummaryMap> {
+        _ = self.tabs.remove(self.active);
+        _ = self.titles.remove(self.active);
+        self.summary_map.merge(summary_map);
+        if self.tabs.is_empty() {
+            return Some(std::mem::take(&mut self.summary_map));
+        }
+        if self.active == self.tabs.len() {
+            self.active = 0;
+        }
+        None
+    }
+
+    pub fn input(&mut self, event: Event) -> Result<SnapshotResult> {
+        if self.tabs.len() > 1 && self.tabs[self.active].is_browsing() {
+            if let Event::Key(key) = &event {
+                if key.kind == KeyEventKind::Press {
+                    match key.code {
+                        KeyCode::Tab => {
+                            self.select_next();
+                            return Ok(SnapshotResult::None);
+                        }
+                        KeyCode::BackTab => {
+                            self.select_previous();
+                            return Ok(SnapshotResult::None);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        match self.tabs[self.active].input(event)? {
+            SnapshotResult::Return(summary_map) => Ok(self
+                .close(summary_map)
+                .map_or(SnapshotResult::None, SnapshotResult::Return)),
+            result => Ok(result),
+        }
+    }
+
+    /// Take the job to start in the background of any tab
+    pub fn take_job(&mut self) -> Option<Job<'a>> {
+        self.tabs.iter_mut().find_map(Snapshot::take_job)
+    }
+
+    /// Update all tabs with the results of their background jobs, also the inactive ones
+    ///
+    /// Returns whether jobs are still running.
+    pub fn update(&mut self) -> bool {
+        self.tabs
+            .iter_mut()
+            .fold(false, |running, tab| tab.update() || running)
+    }
+
+    pub fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
+        if self.tabs.len() == 1 {
+            self.tabs[0].draw(area, f);
+            return;
+        }
+
+        let rects = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).split(area);
+        let tabs = Tabs::new(self.titles.iter().map(String::as_str))
+            .select(self.active)
+            .style(
+                Style::new()
+                    .fg(tailwind::SLATE.c200)
+                    .bg(tailwind::SLATE.c950),
+            )
+            .highlight_style(
+                Style::new()
+                    .fg(tailwind::SLATE.c950)
+                    .bg(tailwind::BLUE.c400)
+                    .add_modifier(Modifier::BOLD),
+            );
+        f.render_widget(tabs, rects[0]);
+        self.tabs[self.active].draw(rects[1], f);
+    }
+}
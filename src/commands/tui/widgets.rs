@@ -1,6 +1,7 @@
 mod popup;
 mod prompt;
 mod select_table;
+mod sized_bar_chart;
 mod sized_gauge;
 mod sized_paragraph;
 mod sized_table;
@@ -11,6 +12,7 @@ pub use popup::*;
 pub use prompt::*;
 use ratatui::widgets::block::Title;
 pub use select_table::*;
+pub use sized_bar_chart::*;
 pub use sized_gauge::*;
 pub use sized_paragraph::*;
 pub use sized_table::*;
@@ -21,8 +23,8 @@ use crossterm::event::Event;
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use ratatui::prelude::*;
 use ratatui::widgets::{
-    Block, Clear, Gauge, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Table,
-    TableState,
+    Bar, BarChart, BarGroup, Block, Clear, Gauge, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+    ScrollbarState, Table, TableState,
 };
 
 pub trait ProcessEvent {
@@ -103,3 +105,15 @@ pub fn popup_gauge(
         Block::bordered().title(title),
     ))
 }
+
+pub type PopUpBarChart = PopUp<WithBlock<SizedBarChart>>;
+pub fn popup_bar_chart(
+    title: impl Into<Title<'static>>,
+    bars: Vec<(String, u64, String)>,
+    style: Style,
+) -> PopUpBarChart {
+    PopUp(WithBlock::new(
+        SizedBarChart::new(bars, style),
+        Block::bordered().title(title),
+    ))
+}
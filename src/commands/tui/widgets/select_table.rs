@@ -38,10 +38,13 @@ pub struct SelectTable {
     rows: usize,
     rows_display: usize,
     row_height: usize,
+    wrap_around: bool, // whether Up/Down wrap around at the first/last row
 }
 
 impl SelectTable {
-    pub fn new(header: Vec<Text<'static>>) -> Self {
+    /// Create a table with the given `header`; `wrap_around` sets whether Up/Down wrap around at
+    /// the first/last row
+    pub fn new(header: Vec<Text<'static>>, wrap_around: bool) -> Self {
         let table = Table::default();
 
         Self {
@@ -52,9 +55,15 @@ impl SelectTable {
             rows: 0,
             rows_display: 0,
             row_height: 0,
+            wrap_around,
         }
     }
 
+    /// Set the header, which is used when the content is set the next time
+    pub fn set_header(&mut self, header: Vec<Text<'static>>) {
+        self.header = header;
+    }
+
     pub fn set_content(&mut self, content: Vec<Vec<Text<'static>>>, row_height: usize) {
         let colors = TableColors::new(&tailwind::BLUE);
         let selected_style = Style::default()
@@ -117,7 +126,7 @@ impl SelectTable {
 
     pub fn go_forward(&mut self, step: usize) {
         if let Some(selected_old) = self.state.selected() {
-            let selected = (selected_old + step).min(self.rows - 1);
+            let selected = (selected_old + step).min(self.rows.saturating_sub(1));
             self.set_to(selected);
         }
     }
@@ -141,6 +150,22 @@ impl SelectTable {
         self.go_back(1);
     }
 
+    /// Select the next row or, when wrapping around, the first row if the last row is selected
+    fn next_wrapping(&mut self) {
+        match self.state.selected() {
+            Some(selected) if self.wrap_around && selected + 1 >= self.rows => self.home(),
+            _ => self.next(),
+        }
+    }
+
+    /// Select the previous row or, when wrapping around, the last row if the first row is selected
+    fn previous_wrapping(&mut self) {
+        match self.state.selected() {
+            Some(0) if self.wrap_around => self.end(),
+            _ => self.previous(),
+        }
+    }
+
     pub fn page_up(&mut self) {
         self.go_back(self.rows_display);
     }
@@ -153,12 +178,13 @@ impl SelectTable {
 
     pub fn end(&mut self) {
         if self.state.selected().is_some() {
-            self.set_to(self.rows - 1);
+            self.set_to(self.rows.saturating_sub(1));
         }
     }
 
+    /// Set the number of rows of the drawn area (including the header row) to compute the page size
     pub fn set_rows(&mut self, rows: usize) {
-        self.rows_display = rows / self.row_height;
+        self.rows_display = (rows.saturating_sub(1) / self.row_height.max(1)).max(1);
     }
 }
 
@@ -168,8 +194,8 @@ impl ProcessEvent for SelectTable {
         use KeyCode::{Down, End, Home, PageDown, PageUp, Up};
         match event {
             Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-                Down => self.next(),
-                Up => self.previous(),
+                Down => self.next_wrapping(),
+                Up => self.previous_wrapping(),
                 PageDown => self.page_down(),
                 PageUp => self.page_up(),
                 Home => self.home(),
@@ -0,0 +1,61 @@
+use super::{Bar, BarChart, BarGroup, Direction, Draw, Frame, Line, Rect, SizedWidget, Style};
+
+/// Width of the bars in addition to the labels
+const BAR_WIDTH: u16 = 50;
+
+/// A horizontal bar chart with one line per bar
+pub struct SizedBarChart {
+    chart: BarChart<'static>,
+    height: Option<u16>,
+    width: Option<u16>,
+}
+
+impl SizedBarChart {
+    /// Create a bar chart from `(label, value, text)` triples; `text` is shown within the bar
+    pub fn new(bars: Vec<(String, u64, String)>, style: Style) -> Self {
+        let height = bars.len().try_into().ok();
+        let label_width = bars
+            .iter()
+            .map(|(label, _, _)| Line::from(label.as_str()).width())
+            .max()
+            .unwrap_or_default();
+        let width = u16::try_from(label_width + 1)
+            .ok()
+            .and_then(|width| width.checked_add(BAR_WIDTH));
+        let bars: Vec<_> = bars
+            .into_iter()
+            .map(|(label, value, text)| {
+                Bar::default()
+                    .label(Line::from(label))
+                    .value(value)
+                    .text_value(text)
+            })
+            .collect();
+        let chart = BarChart::default()
+            .direction(Direction::Horizontal)
+            .bar_width(1)
+            .bar_gap(0)
+            .bar_style(style)
+            .data(BarGroup::default().bars(&bars));
+        Self {
+            chart,
+            height,
+            width,
+        }
+    }
+}
+
+impl SizedWidget for SizedBarChart {
+    fn width(&self) -> Option<u16> {
+        self.width
+    }
+    fn height(&self) -> Option<u16> {
+        self.height
+    }
+}
+
+impl Draw for SizedBarChart {
+    fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
+        f.render_widget(self.chart.clone(), area);
+    }
+}
@@ -1,12 +1,17 @@
 use super::{Draw, Event, Frame, KeyCode, KeyEvent, ProcessEvent, Rect, SizedWidget, Style};
 
+use ratatui::style::Color;
+
 use crossterm::event::KeyModifiers;
+use itertools::Itertools;
 use tui_textarea::{CursorMove, TextArea};
 
 pub struct TextInput {
     textarea: TextArea<'static>,
     lines: u16,
     changeable: bool,
+    top: usize,    // first line shown when last drawn
+    height: usize, // number of lines shown when last drawn
 }
 
 pub enum TextInputResult {
@@ -30,8 +35,99 @@ impl TextInput {
             textarea,
             lines,
             changeable,
+            top: 0,
+            height: usize::from(lines),
         }
     }
+
+    /// The current text of the input
+    pub fn text(&self) -> String {
+        self.textarea.lines().join("\n")
+    }
+
+    /// The lines of the text currently shown
+    pub fn visible_text(&self) -> String {
+        self.textarea
+            .lines()
+            .iter()
+            .skip(self.top)
+            .take(self.height)
+            .join("\n")
+    }
+
+    /// The index of the first line shown and the total number of lines
+    pub fn top_line(&self) -> (usize, usize) {
+        (self.top, self.textarea.lines().len())
+    }
+
+    /// Scroll such that the line with index `line` (clamped to the last line) is shown at the top
+    pub fn goto_line(&mut self, line: usize) {
+        let line = line.min(self.textarea.lines().len().saturating_sub(1));
+        let delta = i16::try_from(line.abs_diff(self.top)).unwrap_or(i16::MAX);
+        let delta = if line < self.top { -delta } else { delta };
+        self.textarea.scroll((delta, 0));
+        self.textarea
+            .move_cursor(CursorMove::Jump(u16::try_from(line).unwrap_or(u16::MAX), 0));
+        self.top = line;
+    }
+
+    /// Whether the whole text fits into the shown area
+    pub fn fully_visible(&self) -> bool {
+        self.textarea.lines().len() <= self.height
+    }
+
+    /// Show or hide line numbers
+    pub fn toggle_line_numbers(&mut self) {
+        if self.textarea.line_number_style().is_some() {
+            self.textarea.remove_line_number();
+        } else {
+            self.textarea
+                .set_line_number_style(Style::default().fg(Color::DarkGray));
+        }
+    }
+
+    /// Highlight all occurrences of `query` and move to the first one after the cursor
+    ///
+    /// Returns the number of matches. An empty `query` removes the highlighting.
+    pub fn search(&mut self, query: &str) -> usize {
+        if self
+            .textarea
+            .set_search_pattern(escape_regex(query))
+            .is_err()
+            || query.is_empty()
+        {
+            return 0;
+        }
+        self.textarea
+            .set_search_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+        _ = self.textarea.search_forward(true);
+        self.textarea
+            .lines()
+            .iter()
+            .map(|line| line.matches(query).count())
+            .sum()
+    }
+
+    /// Move to the next (or previous) match of the search, wrapping around at the end
+    pub fn next_match(&mut self, forward: bool) {
+        if forward {
+            _ = self.textarea.search_forward(false);
+        } else {
+            _ = self.textarea.search_back(false);
+        }
+    }
+}
+
+/// Escape all regex meta characters so that `text` is matched literally
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 impl SizedWidget for TextInput {
@@ -42,6 +138,14 @@ impl SizedWidget for TextInput {
 
 impl Draw for TextInput {
     fn draw(&mut self, area: Rect, f: &mut Frame<'_>) {
+        // keep track of the shown lines, scrolling like the textarea does to keep the cursor visible
+        let row = self.textarea.cursor().0;
+        self.height = usize::from(area.height);
+        if row < self.top {
+            self.top = row;
+        } else if self.height > 0 && row >= self.top + self.height {
+            self.top = row + 1 - self.height;
+        }
         f.render_widget(&self.textarea, area);
     }
 }
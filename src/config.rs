@@ -23,11 +23,13 @@ use log::Level;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
-#[cfg(not(all(feature = "mount", feature = "webdav")))]
+#[cfg(not(all(feature = "mount", feature = "tui", feature = "webdav")))]
 use toml::Value;
 
 #[cfg(feature = "mount")]
 use crate::commands::mount::MountCmd;
+#[cfg(feature = "tui")]
+use crate::commands::tui::TuiOptions;
 #[cfg(feature = "webdav")]
 use crate::commands::webdav::WebDavCmd;
 
@@ -35,6 +37,7 @@ use crate::{
     commands::{backup::BackupCmd, copy::CopyCmd, forget::ForgetOptions},
     config::{hooks::Hooks, progress_options::ProgressOptions},
     filtering::SnapshotFilter,
+    helpers::DisplayTimeZone,
     repository::AllRepositoryOptions,
 };
 
@@ -88,6 +91,15 @@ pub struct RusticConfig {
     #[clap(skip)]
     #[merge(skip)]
     pub webdav: Option<Value>,
+
+    /// interactive UI options
+    #[cfg(feature = "tui")]
+    #[clap(skip)]
+    pub tui: TuiOptions,
+    #[cfg(not(feature = "tui"))]
+    #[clap(skip)]
+    #[merge(skip)]
+    pub tui: Option<Value>,
 }
 
 impl Display for RusticConfig {
@@ -168,6 +180,18 @@ pub struct GlobalOptions {
     #[merge(strategy=conflate::bool::overwrite_false)]
     pub check_index: bool,
 
+    /// Display times in UTC instead of local time (same as `--timezone utc`)
+    #[clap(long, global = true, env = "RUSTIC_UTC", conflicts_with = "timezone")]
+    #[merge(strategy=conflate::bool::overwrite_false)]
+    pub utc: bool,
+
+    /// Display times in this time zone: "local", "utc" or an offset from UTC like "+02:00"
+    /// [default: local]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[clap(long, global = true, env = "RUSTIC_TIMEZONE", value_name = "TIMEZONE")]
+    #[merge(strategy=conflate::option::overwrite_none)]
+    pub timezone: Option<DisplayTimeZone>,
+
     /// Use this log level [default: info]
     #[clap(long, global = true, env = "RUSTIC_LOG_LEVEL")]
     #[merge(strategy=conflate::option::overwrite_none)]
@@ -240,6 +264,15 @@ pub fn parse_labels(s: &str) -> Result<BTreeMap<String, String>> {
 }
 
 impl GlobalOptions {
+    /// The time zone to display times in; an explicit `--timezone` takes precedence over `--utc`
+    pub fn time_zone(&self) -> DisplayTimeZone {
+        self.timezone.unwrap_or(if self.utc {
+            DisplayTimeZone::Utc
+        } else {
+            DisplayTimeZone::Local
+        })
+    }
+
     pub fn is_metrics_configured(&self) -> bool {
         self.prometheus.is_some() || self.opentelemetry.is_some()
     }
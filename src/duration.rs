@@ -0,0 +1,173 @@
+//! Parse durations relative to a point in time, like "7d", "24h", "2w" or "3mo"
+
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Local, Months, TimeDelta};
+
+/// The length of a unit of durations
+#[derive(Clone, Copy)]
+enum Unit {
+    Seconds(i64),
+    Months(u32),
+}
+
+/// The units of durations with their names
+const UNITS: &[(&[&str], Unit)] = &[
+    (&["s", "sec", "secs", "second", "seconds"], Unit::Seconds(1)),
+    (
+        &["m", "min", "mins", "minute", "minutes"],
+        Unit::Seconds(60),
+    ),
+    (&["h", "hr", "hrs", "hour", "hours"], Unit::Seconds(60 * 60)),
+    (&["d", "day", "days"], Unit::Seconds(24 * 60 * 60)),
+    (&["w", "week", "weeks"], Unit::Seconds(7 * 24 * 60 * 60)),
+    (&["mo", "month", "months"], Unit::Months(1)),
+    (&["y", "year", "years"], Unit::Months(12)),
+];
+
+/// A duration relative to a point in time, e.g. "7d", "3mo" or "1d12h"
+///
+/// Months and years are calendar months and years, all other units have a fixed length.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RelativeDuration {
+    months: u32,
+    delta: TimeDelta,
+}
+
+impl RelativeDuration {
+    /// The point in time this duration before `time`
+    ///
+    /// # Errors
+    ///
+    /// If the resulting time is out of range
+    pub fn before(self, time: DateTime<Local>) -> Result<DateTime<Local>> {
+        time.checked_sub_months(Months::new(self.months))
+            .and_then(|time| time.checked_sub_signed(self.delta))
+            .ok_or_else(|| anyhow!("duration is too large"))
+    }
+
+    /// The point in time this duration before now
+    ///
+    /// # Errors
+    ///
+    /// If the resulting time is out of range
+    pub fn before_now(self) -> Result<DateTime<Local>> {
+        self.before(Local::now())
+    }
+}
+
+impl FromStr for RelativeDuration {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut duration = Self::default();
+        let mut rest = s.trim();
+        if rest.is_empty() {
+            bail!("empty duration");
+        }
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if digits == 0 {
+                bail!("invalid duration {s:?}: expected a number before {rest:?}");
+            }
+            let value: u32 = rest[..digits]
+                .parse()
+                .map_err(|_| anyhow!("invalid duration {s:?}: number is too large"))?;
+            rest = rest[digits..].trim_start();
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let unit = &rest[..len];
+            rest = rest[len..].trim_start();
+
+            let too_large = || anyhow!("invalid duration {s:?}: duration is too large");
+            match UNITS.iter().find(|(names, _)| names.contains(&unit)) {
+                Some((_, Unit::Months(months))) => {
+                    let months = value.checked_mul(*months).ok_or_else(too_large)?;
+                    duration.months = duration.months.checked_add(months).ok_or_else(too_large)?;
+                }
+                Some((_, Unit::Seconds(seconds))) => {
+                    let delta =
+                        TimeDelta::try_seconds(i64::from(value) * seconds).ok_or_else(too_large)?;
+                    duration.delta = duration.delta.checked_add(&delta).ok_or_else(too_large)?;
+                }
+                None if unit.is_empty() => {
+                    bail!("invalid duration {s:?}: missing unit (use s, m, h, d, w, mo or y)");
+                }
+                None => bail!(
+                    "invalid duration {s:?}: unknown unit {unit:?} (use s, m, h, d, w, mo or y)"
+                ),
+            }
+        }
+        Ok(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("0d", 0, 0)]
+    #[case("0s", 0, 0)]
+    #[case("30s", 0, 30)]
+    #[case("90m", 0, 90 * 60)]
+    #[case("24h", 0, 24 * 3600)]
+    #[case("7d", 0, 7 * 86400)]
+    #[case("2w", 0, 14 * 86400)]
+    #[case("3mo", 3, 0)]
+    #[case("1y", 12, 0)]
+    #[case("1d12h", 0, 36 * 3600)]
+    #[case("2 weeks", 0, 14 * 86400)]
+    #[case(" 1y 6mo ", 18, 0)]
+    #[case("4294967295s", 0, 4_294_967_295)]
+    #[case("4294967295mo", u32::MAX, 0)]
+    fn parse_duration_passes(#[case] s: &str, #[case] months: u32, #[case] seconds: i64) {
+        let duration: RelativeDuration = s.parse().unwrap();
+        assert_eq!(duration.months, months);
+        assert_eq!(duration.delta, TimeDelta::seconds(seconds));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("d")]
+    #[case("7")]
+    #[case("5x")]
+    #[case("7d-")]
+    #[case("-7d")]
+    #[case("1.5d")]
+    #[case("4294967296d")]
+    #[case("99999999999999999999d")]
+    #[case("4294967295y")]
+    #[case("4294967295mo1mo")]
+    fn parse_duration_fails(#[case] s: &str) {
+        assert!(s.parse::<RelativeDuration>().is_err());
+    }
+
+    #[rstest]
+    #[case("0d", (2024, 5, 31, 12))]
+    #[case("1d", (2024, 5, 30, 12))]
+    #[case("12h", (2024, 5, 31, 0))]
+    #[case("1mo", (2024, 4, 30, 12))]
+    #[case("1y1d", (2023, 5, 30, 12))]
+    fn before_passes(#[case] s: &str, #[case] expected: (i32, u32, u32, u32)) {
+        let time = Local.with_ymd_and_hms(2024, 5, 31, 12, 0, 0).unwrap();
+        let (year, month, day, hour) = expected;
+        let expected = Local
+            .with_ymd_and_hms(year, month, day, hour, 0, 0)
+            .unwrap();
+        let duration: RelativeDuration = s.parse().unwrap();
+        assert_eq!(duration.before(time).unwrap(), expected);
+    }
+
+    #[test]
+    fn before_fails_for_large_durations() {
+        let duration: RelativeDuration = "4294967295mo".parse().unwrap();
+        assert!(duration.before_now().is_err());
+    }
+}
@@ -1,8 +1,23 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
 use bytesize::ByteSize;
+use chrono::{
+    DateTime, FixedOffset, Local, TimeDelta, Utc,
+    format::{Item, StrftimeItems},
+};
 use comfy_table::{
     Attribute, Cell, CellAlignment, ContentArrangement, Table, presets::ASCII_MARKDOWN,
 };
 
+use anyhow::bail;
+use itertools::Itertools;
+use rustic_core::repofile::{SnapshotFile, SnapshotId};
+
+use crate::repository::CliIndexedRepo;
+
 /// Helpers for table output
 /// Create a new bold cell
 pub fn bold_cell<T: ToString>(s: T) -> Cell {
@@ -45,3 +60,334 @@ pub fn table_right_from<I: IntoIterator<Item = T>, T: ToString>(start: usize, ti
 pub fn bytes_size_to_string(b: u64) -> String {
     ByteSize(b).to_string_as(true)
 }
+
+/// Time zone in which times are displayed, set by `--timezone` or `--utc`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayTimeZone {
+    /// the local time zone of the system
+    #[default]
+    Local,
+    /// UTC
+    Utc,
+    /// a fixed offset from UTC, e.g. "+02:00"
+    Fixed(FixedOffset),
+}
+
+impl DisplayTimeZone {
+    /// Format `time` in this time zone using the strftime format `fmt`
+    #[must_use]
+    pub fn format(self, time: &DateTime<Local>, fmt: &str) -> String {
+        match self {
+            Self::Local => time.format(fmt).to_string(),
+            Self::Utc => time.with_timezone(&Utc).format(fmt).to_string(),
+            Self::Fixed(offset) => time.with_timezone(&offset).format(fmt).to_string(),
+        }
+    }
+}
+
+impl FromStr for DisplayTimeZone {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "local" => Self::Local,
+            "utc" | "z" => Self::Utc,
+            _ => {
+                let Some(offset) = parse_offset(s) else {
+                    bail!(
+                        "invalid time zone {s}, use \"local\", \"utc\" or an offset like \"+02:00\""
+                    );
+                };
+                Self::Fixed(offset)
+            }
+        })
+    }
+}
+
+impl Display for DisplayTimeZone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => write!(f, "local"),
+            Self::Utc => write!(f, "utc"),
+            Self::Fixed(offset) => write!(f, "{offset}"),
+        }
+    }
+}
+
+/// Parse an offset from UTC like "+02:00", "-0530" or "+2"
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some(parts) => parts,
+        None if rest.len() == 4 => rest.split_at_checked(2)?,
+        None => (rest, "0"),
+    };
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if !(0..24).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Format used to show times of entries, e.g. in `ls --long` or the interactive UI
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// relative to now, e.g. "3 days ago"; entries older than a year are shown as absolute time
+    Relative,
+    /// RFC 3339 / ISO 8601, e.g. "2024-01-31T12:00:00+01:00"
+    Iso,
+    /// a strftime format string, e.g. "%Y-%m-%d %H:%M:%S"
+    Custom(String),
+}
+
+impl TimeFormat {
+    /// Absolute format used for relative times of old or future entries
+    const ABSOLUTE: &str = "%Y-%m-%d %H:%M:%S";
+
+    /// Format `time` using this format, showing absolute times in the time zone `zone`
+    #[must_use]
+    pub fn format(&self, time: &DateTime<Local>, zone: DisplayTimeZone) -> String {
+        match self {
+            Self::Relative => relative_time(Local::now() - *time)
+                .unwrap_or_else(|| zone.format(time, Self::ABSOLUTE)),
+            Self::Iso => zone.format(time, "%Y-%m-%dT%H:%M:%S%:z"),
+            Self::Custom(fmt) => zone.format(time, fmt),
+        }
+    }
+}
+
+impl FromStr for TimeFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "relative" => Self::Relative,
+            "iso" => Self::Iso,
+            fmt => {
+                if StrftimeItems::new(fmt).any(|item| item == Item::Error) {
+                    bail!("invalid time format {fmt}");
+                }
+                Self::Custom(fmt.to_string())
+            }
+        })
+    }
+}
+
+impl Display for TimeFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Relative => write!(f, "relative"),
+            Self::Iso => write!(f, "iso"),
+            Self::Custom(fmt) => write!(f, "{fmt}"),
+        }
+    }
+}
+
+/// Describe a time `age` ago, e.g. "3 days ago"
+///
+/// Returns `None` for times in the future or more than a year ago.
+fn relative_time(age: TimeDelta) -> Option<String> {
+    let (count, unit) = match age.num_seconds() {
+        ..0 => return None,
+        secs @ 0..60 => (secs, "second"),
+        secs @ 60..3600 => (secs / 60, "minute"),
+        secs @ 3600..86_400 => (secs / 3600, "hour"),
+        secs @ 86_400..31_536_000 => (secs / 86_400, "day"),
+        _ => return None,
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    Some(format!("{count} {unit}{plural} ago"))
+}
+
+/// Minimum number of bytes to request per read of file contents
+pub const MIN_CHUNK_SIZE: u64 = 4 * 1024;
+
+/// Maximum number of bytes to request per read of file contents
+pub const MAX_CHUNK_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Default number of bytes to request per read of file contents
+pub const DEFAULT_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Clamp a chunk size to the allowed bounds
+#[must_use]
+pub fn clamp_chunk_size(size: u64) -> usize {
+    // MAX_CHUNK_SIZE fits into usize on all supported platforms
+    size.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+        .try_into()
+        .unwrap_or(usize::MAX)
+}
+
+/// Parse a human readable chunk size and validate it is within the allowed bounds
+///
+/// # Errors
+///
+/// If the size cannot be parsed or is out of bounds
+pub fn parse_chunk_size(s: &str) -> Result<usize, String> {
+    let size: ByteSize = s.trim().parse()?;
+    if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&size.0) {
+        return Err(format!(
+            "chunk size must be between {} and {}",
+            bytes_size_to_string(MIN_CHUNK_SIZE),
+            bytes_size_to_string(MAX_CHUNK_SIZE)
+        ));
+    }
+    Ok(clamp_chunk_size(size.0))
+}
+
+/// Score how well `pattern` fuzzy-matches `text`
+///
+/// All characters of `pattern` must appear in `text` in the same order (ignoring case).
+/// Consecutive characters and matches at the start of words get a higher score.
+/// Returns `None` if `pattern` doesn't match.
+#[must_use]
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<usize> {
+    let text: Vec<_> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last = None;
+    for c in pattern.to_lowercase().chars() {
+        let idx = pos + text[pos..].iter().position(|&t| t == c)?;
+        score += 1;
+        if last.is_some_and(|last| last + 1 == idx) {
+            score += 2;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += 1;
+        }
+        last = Some(idx);
+        pos = idx + 1;
+    }
+    Some(score)
+}
+
+/// Error if no snapshot (or no path within a snapshot) matches what was asked for
+///
+/// This allows callers to tell missing snapshots apart from other errors, e.g. of the backend.
+#[derive(Debug)]
+pub struct NotFound(pub String);
+
+impl Display for NotFound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// Check that the snapshot id prefix of a `SNAPSHOT[:PATH]` argument matches exactly one snapshot
+///
+/// Arguments which are no id prefixes (like `latest`) are accepted unchecked.
+///
+/// # Errors
+///
+/// If no or more than one snapshot id starts with the prefix; a [`NotFound`] error if there is none
+pub fn check_snapshot_prefix(repo: &CliIndexedRepo, snap: &str) -> anyhow::Result<()> {
+    let prefix = snap.split_once(':').map_or(snap, |(prefix, _)| prefix);
+    if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(());
+    }
+    let candidates: Vec<_> = repo
+        .list::<SnapshotId>()?
+        .map(|id| id.to_hex().to_string())
+        .filter(|id| id.starts_with(prefix))
+        .collect();
+    match candidates.as_slice() {
+        [] => Err(NotFound(format!("no snapshot found matching prefix '{prefix}'")).into()),
+        [_] => Ok(()),
+        _ => bail!(
+            "multiple snapshots match prefix '{prefix}', please be more specific:\n{}",
+            candidates.iter().map(|id| format!("  {id}")).join("\n")
+        ),
+    }
+}
+
+/// Resolve a snapshot argument of the form `~DESCRIPTION[:PATH]` by fuzzy matching snapshot descriptions
+///
+/// Other arguments are returned unchanged.
+///
+/// # Errors
+///
+/// If no or more than one snapshot description matches; a [`NotFound`] error if there is none
+pub fn resolve_snapshot_description(
+    repo: &CliIndexedRepo,
+    snap: &str,
+    filter: impl FnMut(&SnapshotFile) -> bool,
+) -> anyhow::Result<String> {
+    let Some(pattern) = snap.strip_prefix('~') else {
+        return Ok(snap.to_string());
+    };
+    let (pattern, path) = pattern
+        .split_once(':')
+        .map_or((pattern, None), |(pattern, path)| (pattern, Some(path)));
+
+    let mut matches: Vec<_> = repo
+        .get_matching_snapshots(filter)?
+        .into_iter()
+        .filter_map(|sn| Some((fuzzy_score(pattern, sn.description.as_deref()?)?, sn)))
+        .collect();
+    matches.sort_by(|(score1, sn1), (score2, sn2)| {
+        score2.cmp(score1).then_with(|| sn2.time.cmp(&sn1.time))
+    });
+
+    match matches.as_slice() {
+        [] => Err(NotFound(format!("no snapshot description matches {pattern}")).into()),
+        [(_, sn)] => Ok(path.map_or_else(|| sn.id.to_string(), |path| format!("{}:{path}", sn.id))),
+        _ => {
+            let candidates = matches
+                .iter()
+                .map(|(score, sn)| {
+                    let description = sn.description.as_deref().unwrap_or_default();
+                    format!(
+                        "  {} (score {score}): {}",
+                        sn.id,
+                        description.lines().next().unwrap_or_default()
+                    )
+                })
+                .join("\n");
+            bail!(
+                "multiple snapshot descriptions match {pattern}, please use one of:\n{candidates}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("local", DisplayTimeZone::Local)]
+    #[case("UTC", DisplayTimeZone::Utc)]
+    #[case("Z", DisplayTimeZone::Utc)]
+    #[case("+02:00", DisplayTimeZone::Fixed(FixedOffset::east_opt(7200).unwrap()))]
+    #[case("-0530", DisplayTimeZone::Fixed(FixedOffset::west_opt(19_800).unwrap()))]
+    #[case("+2", DisplayTimeZone::Fixed(FixedOffset::east_opt(7200).unwrap()))]
+    fn display_time_zone_from_str_passes(#[case] s: &str, #[case] expected: DisplayTimeZone) {
+        assert_eq!(s.parse::<DisplayTimeZone>().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("Europe/Berlin")]
+    #[case("02:00")]
+    #[case("+24:00")]
+    #[case("+02:60")]
+    #[case("+02:")]
+    fn display_time_zone_from_str_fails(#[case] s: &str) {
+        assert!(s.parse::<DisplayTimeZone>().is_err());
+    }
+
+    #[rstest]
+    #[case(DisplayTimeZone::Utc, "2024-01-31 11:00")]
+    #[case(DisplayTimeZone::Fixed(FixedOffset::east_opt(7200).unwrap()), "2024-01-31 13:00")]
+    fn display_time_zone_format_passes(#[case] zone: DisplayTimeZone, #[case] expected: &str) {
+        let time = DateTime::parse_from_rfc3339("2024-01-31T12:00:00+01:00")
+            .unwrap()
+            .with_timezone(&Local);
+        assert_eq!(zone.format(&time, "%Y-%m-%d %H:%M"), expected);
+        assert_eq!(zone.to_string().parse::<DisplayTimeZone>().unwrap(), zone);
+    }
+}
@@ -62,11 +62,13 @@ Application based on the [Abscissa] framework.
 pub mod application;
 pub(crate) mod commands;
 pub(crate) mod config;
+pub(crate) mod duration;
 pub(crate) mod error;
 pub(crate) mod filtering;
 pub(crate) mod helpers;
 #[cfg(any(feature = "prometheus", feature = "opentelemetry"))]
 pub(crate) mod metrics;
+pub(crate) mod mime;
 pub(crate) mod repository;
 
 // rustic_cli Public API
@@ -0,0 +1,185 @@
+//! Detect the content type of files by sniffing their magic bytes
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rustic_core::{
+    DataId, IndexedFull, Repository,
+    repofile::{Node, NodeType},
+};
+
+/// Number of bytes to read from the start of a file to detect its content type
+pub const SNIFF_LEN: usize = 512;
+
+/// Known magic bytes: offset, magic and content type
+const MAGIC: &[(usize, &[u8], &str)] = &[
+    (0, b"\xFF\xD8\xFF", "image/jpeg"),
+    (0, b"\x89PNG\r\n\x1A\n", "image/png"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (0, b"II*\x00", "image/tiff"),
+    (0, b"MM\x00*", "image/tiff"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1F\x8B", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xFD7zXZ\x00", "application/x-xz"),
+    (0, b"\x28\xB5\x2F\xFD", "application/zstd"),
+    (0, b"7z\xBC\xAF\x27\x1C", "application/x-7z-compressed"),
+    (0, b"Rar!\x1A\x07", "application/vnd.rar"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"\x7FELF", "application/x-executable"),
+    (0, b"MZ", "application/x-msdownload"),
+    (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"\x1A\x45\xDF\xA3", "video/webm"),
+];
+
+/// Detect the content type of the given start of a file
+#[must_use]
+pub fn sniff(data: &[u8]) -> &'static str {
+    if data.is_empty() {
+        return "application/x-empty";
+    }
+    if data.starts_with(b"RIFF") && data.len() >= 12 {
+        match &data[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            b"AVI " => return "video/x-msvideo",
+            _ => {}
+        }
+    }
+    if let Some((_, _, mime)) = MAGIC
+        .iter()
+        .find(|(offset, magic, _)| data.get(*offset..).is_some_and(|d| d.starts_with(magic)))
+    {
+        return *mime;
+    }
+    if is_text(data) {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// Check if the data looks like text, allowing an incomplete UTF-8 sequence at the end
+fn is_text(data: &[u8]) -> bool {
+    if data.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(data) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+/// The content type of a node which is not a regular file
+#[must_use]
+pub fn node_type_mime(node: &Node) -> Option<&'static str> {
+    match node.node_type {
+        NodeType::File => None,
+        NodeType::Dir => Some("inode/directory"),
+        NodeType::Symlink { .. } => Some("inode/symlink"),
+        NodeType::Dev { .. } => Some("inode/blockdevice"),
+        NodeType::Chardev { .. } => Some("inode/chardevice"),
+        NodeType::Fifo => Some("inode/fifo"),
+        NodeType::Socket => Some("inode/socket"),
+    }
+}
+
+/// Content types of files, cached by the id of their first data blob
+#[derive(Default)]
+pub struct MimeCache(HashMap<DataId, &'static str>);
+
+impl MimeCache {
+    /// Get the content type of a node, reading the start of the file if not yet cached
+    ///
+    /// # Errors
+    ///
+    /// If the file contents cannot be read
+    pub fn get<P, S: IndexedFull>(
+        &mut self,
+        repo: &Repository<P, S>,
+        node: &Node,
+    ) -> Result<&'static str> {
+        if let Some(mime) = node_type_mime(node) {
+            return Ok(mime);
+        }
+        let Some(id) = node.content.as_ref().and_then(|content| content.first()) else {
+            return Ok(sniff(&[]));
+        };
+        if let Some(mime) = self.0.get(id).copied() {
+            return Ok(mime);
+        }
+        let length = usize::try_from(node.meta.size).map_or(SNIFF_LEN, |size| size.min(SNIFF_LEN));
+        let data = repo.open_file(node)?.read_at(repo, 0, length)?;
+        let mime = sniff(&data);
+        _ = self.0.insert(*id, mime);
+        Ok(mime)
+    }
+
+    /// Get the content type of a node if it is already known without reading file contents
+    #[must_use]
+    pub fn cached(&self, node: &Node) -> Option<&'static str> {
+        node_type_mime(node).or_else(|| {
+            node.content
+                .as_ref()
+                .and_then(|content| content.first())
+                .map_or(Some(sniff(&[])), |id| self.0.get(id).copied())
+        })
+    }
+}
+
+/// Check if a content type matches a pattern like `image/*`, where `*` matches any characters
+#[must_use]
+pub fn mime_matches(pattern: &str, mime: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = mime.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<_> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // no wildcard: exact match
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(b"\xFF\xD8\xFF\xE0\x00\x10JFIF", "image/jpeg")]
+    #[case(b"\x89PNG\r\n\x1A\n\x00", "image/png")]
+    #[case(b"RIFF\x00\x00\x00\x00WEBPVP8 ", "image/webp")]
+    #[case(b"hello world\n", "text/plain")]
+    #[case(b"\x00\x01\x02", "application/octet-stream")]
+    #[case(b"", "application/x-empty")]
+    fn sniff_detects_content_types(#[case] data: &[u8], #[case] expected: &str) {
+        assert_eq!(sniff(data), expected);
+    }
+
+    #[rstest]
+    #[case("image/*", "image/jpeg", true)]
+    #[case("image/*", "text/plain", false)]
+    #[case("*/plain", "text/plain", true)]
+    #[case("text/plain", "text/plain", true)]
+    #[case("text/plain", "text/plainx", false)]
+    #[case("*", "application/pdf", true)]
+    fn mime_matches_patterns(#[case] pattern: &str, #[case] mime: &str, #[case] expected: bool) {
+        assert_eq!(mime_matches(pattern, mime), expected);
+    }
+}
@@ -4,8 +4,11 @@
 //! application's configuration file and/or command-line options
 //! for specifying it.
 
+pub(crate) mod throttle;
+
 use std::fmt::Debug;
 use std::ops::Deref;
+use std::sync::Arc;
 
 use abscissa_core::Application;
 use anyhow::{Result, anyhow, bail};
@@ -21,6 +24,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     RUSTIC_APP,
     config::{hooks::Hooks, progress_options::ProgressOptions},
+    repository::throttle::{RateLimiter, throttle_backends},
 };
 
 pub(super) mod constants {
@@ -52,7 +56,18 @@ pub type CliIndexedRepo = RusticIndexedRepo<ProgressOptions>;
 
 impl AllRepositoryOptions {
     fn repository<P>(&self, po: P) -> Result<RusticRepo<P>> {
-        let backends = self.be.to_backends()?;
+        self.repository_with_limit(po, None)
+    }
+
+    fn repository_with_limit<P>(
+        &self,
+        po: P,
+        limiter: Option<&Arc<RateLimiter>>,
+    ) -> Result<RusticRepo<P>> {
+        let mut backends = self.be.to_backends()?;
+        if let Some(limiter) = limiter {
+            backends = throttle_backends(&backends, limiter);
+        }
         let repo = Repository::new_with_progress(&self.repo, &backends, po)?;
         Ok(RusticRepo(repo))
     }
@@ -97,6 +112,17 @@ impl AllRepositoryOptions {
         let po = RUSTIC_APP.config().global.progress_options;
         self.run_indexed_with_progress(po, f)
     }
+
+    /// Run `f` on the indexed repository with all backend reads throttled by `limiter`
+    pub fn run_indexed_with_limit<T>(
+        &self,
+        limiter: Option<&Arc<RateLimiter>>,
+        f: impl FnOnce(CliIndexedRepo) -> Result<T>,
+    ) -> Result<T> {
+        let hooks = self.hooks.with_context("repository");
+        let po = RUSTIC_APP.config().global.progress_options;
+        hooks.use_with(|| f(self.repository_with_limit(po, limiter)?.indexed()?))
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,163 @@
+//! Bandwidth throttling for backend reads
+
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use rustic_core::{FileType, Id, ReadBackend, RepositoryBackends, RusticResult, WriteBackend};
+
+/// A rate limiter shared by all (possibly parallel) reads of a repository.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// allowed bytes per second
+    rate: u64,
+    state: Mutex<RateState>,
+}
+
+#[derive(Debug, Default)]
+struct RateState {
+    /// time the next read is allowed to start
+    next: Option<Instant>,
+    /// time of the first read
+    first: Option<Instant>,
+    /// total bytes read
+    bytes: u64,
+}
+
+impl RateLimiter {
+    /// Create a new [`RateLimiter`] allowing `rate` bytes per second.
+    pub fn new(rate: u64) -> Self {
+        Self {
+            rate: rate.max(1),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Account for `len` read bytes and wait until the target rate is met.
+    #[allow(clippy::cast_precision_loss)]
+    fn consume(&self, len: usize) {
+        let now = Instant::now();
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let start = state.next.map_or(now, |next| next.max(now));
+            let next = start + Duration::from_secs_f64(len as f64 / self.rate as f64);
+            state.next = Some(next);
+            _ = state.first.get_or_insert(now);
+            state.bytes += len as u64;
+            next - now
+        };
+        sleep(wait);
+    }
+
+    /// The total number of bytes read
+    pub fn bytes(&self) -> u64 {
+        self.state.lock().unwrap().bytes
+    }
+
+    /// The effective average rate in bytes per second since the first read
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn average_rate(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        let secs = state
+            .first
+            .map_or(0.0, |first| first.elapsed().as_secs_f64());
+        if secs > 0.0 {
+            (state.bytes as f64 / secs) as u64
+        } else {
+            0
+        }
+    }
+}
+
+/// A backend which throttles all reads using a shared [`RateLimiter`]
+struct ThrottledBackend {
+    be: Arc<dyn WriteBackend>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl Debug for ThrottledBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThrottledBackend")
+            .field("location", &self.be.location())
+            .field("limiter", &self.limiter)
+            .finish()
+    }
+}
+
+impl ReadBackend for ThrottledBackend {
+    fn location(&self) -> String {
+        self.be.location()
+    }
+
+    fn list_with_size(&self, tpe: FileType) -> RusticResult<Vec<(Id, u32)>> {
+        self.be.list_with_size(tpe)
+    }
+
+    fn list(&self, tpe: FileType) -> RusticResult<Vec<Id>> {
+        self.be.list(tpe)
+    }
+
+    fn read_full(&self, tpe: FileType, id: &Id) -> RusticResult<Bytes> {
+        let data = self.be.read_full(tpe, id)?;
+        self.limiter.consume(data.len());
+        Ok(data)
+    }
+
+    fn read_partial(
+        &self,
+        tpe: FileType,
+        id: &Id,
+        cacheable: bool,
+        offset: u32,
+        length: u32,
+    ) -> RusticResult<Bytes> {
+        let data = self.be.read_partial(tpe, id, cacheable, offset, length)?;
+        self.limiter.consume(data.len());
+        Ok(data)
+    }
+
+    fn needs_warm_up(&self) -> bool {
+        self.be.needs_warm_up()
+    }
+
+    fn warm_up(&self, tpe: FileType, id: &Id) -> RusticResult<()> {
+        self.be.warm_up(tpe, id)
+    }
+}
+
+impl WriteBackend for ThrottledBackend {
+    fn create(&self) -> RusticResult<()> {
+        self.be.create()
+    }
+
+    fn write_bytes(&self, tpe: FileType, id: &Id, cacheable: bool, buf: Bytes) -> RusticResult<()> {
+        self.be.write_bytes(tpe, id, cacheable, buf)
+    }
+
+    fn remove(&self, tpe: FileType, id: &Id, cacheable: bool) -> RusticResult<()> {
+        self.be.remove(tpe, id, cacheable)
+    }
+}
+
+/// Wrap all backends such that reads are throttled by the given [`RateLimiter`]
+pub fn throttle_backends(
+    backends: &RepositoryBackends,
+    limiter: &Arc<RateLimiter>,
+) -> RepositoryBackends {
+    let throttle = |be: Arc<dyn WriteBackend>| -> Arc<dyn WriteBackend> {
+        Arc::new(ThrottledBackend {
+            be,
+            limiter: limiter.clone(),
+        })
+    };
+    RepositoryBackends::new(
+        throttle(backends.repository()),
+        backends.repo_hot().map(throttle),
+    )
+}